@@ -14,7 +14,7 @@ pub fn generate_ansible_hosts(hosts: &[Host], defaults: &AnsibleDefaults) -> Str
             HostType::LXC => "Proxmox_LXC",
             HostType::Physical => "Physical",
         };
-        grouped.entry(group).or_insert_with(Vec::new).push(host);
+        grouped.entry(group).or_default().push(host);
     }
 
     // Generate output for each group
@@ -69,18 +69,38 @@ mod tests {
                 host_type: HostType::VM,
                 status: "running".to_string(),
                 ip: Some("10.1.2.10".to_string()),
+                ip_unavailable: None,
+                snapshots: None,
                 node: Some("pve1".to_string()),
                 vmid: Some(100),
                 ansible_user: Some("gozy".to_string()),
+                cluster: Some("vs01".to_string()),
+                pool: None,
+                template: false,
+                tags: Vec::new(),
+                cpu: None,
+                mem: None,
+                maxmem: None,
+                uptime: None,
             },
             Host {
                 name: "test-lxc".to_string(),
                 host_type: HostType::LXC,
                 status: "running".to_string(),
                 ip: Some("10.1.2.20".to_string()),
+                ip_unavailable: None,
+                snapshots: None,
                 node: Some("pve1".to_string()),
                 vmid: Some(101),
                 ansible_user: Some("gozy".to_string()),
+                cluster: Some("vs01".to_string()),
+                pool: None,
+                template: false,
+                tags: Vec::new(),
+                cpu: None,
+                mem: None,
+                maxmem: None,
+                uptime: None,
             },
         ];
 
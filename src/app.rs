@@ -1,11 +1,23 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 use arboard::Clipboard;
 use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::collections::HashSet;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
 use tui_input::Input;
 
 use crate::ansible;
-use crate::config::{Config, IpOverride};
-use crate::proxmox::{Host, HostType, ProxmoxClient};
+use crate::auth::{bootstrap_token, Credential, LoginStep, SessionStore, TokenRole};
+use crate::backups::{Backup, BackupMode, Compression};
+use crate::cluster::LogicalCluster;
+use crate::config::{AuthMode, Config, IpOverride, Protection, ProxmoxHost};
+use crate::containers::{self, ContainerSpec, Network, NodeOptions};
+use crate::events::{spawn_refresh, ActionOutcome, AppEvent, ContainerOutcome, EventSender, Form};
+use crate::permissions::{self, Permissions};
+use crate::proxmox::{self, CloneSpec, ClusterStatus, ConnectionReport, GuestAction, Host, HostId, HostType, NodeInfo, PowerAction, ProxmoxClient, StorageInfo};
+use crate::snapshots::{self, CountCache, Snapshot};
+use crate::tasks::{TaskHandle, TaskTracker, TaskUpdate};
+use crate::tls;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum ViewMode {
@@ -13,6 +25,19 @@ pub enum ViewMode {
     Export,
     EditIp,
     Setup,
+    ConfirmAction,
+    Tasks,
+    TaskLog,
+    Clusters,
+    Login,
+    Snapshots,
+    SnapshotCreate,
+    Backups,
+    BackupCreate,
+    BackupRestore,
+    Migrate,
+    Clone,
+    ContainerCreate,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -24,6 +49,40 @@ pub enum SortColumn {
     Node,
 }
 
+/// Which hosts the main table lists: templates are kept apart from guests
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HostFilter {
+    Guests,
+    Templates,
+    All,
+}
+
+impl HostFilter {
+    pub fn label(&self) -> &str {
+        match self {
+            HostFilter::Guests => "guests",
+            HostFilter::Templates => "templates",
+            HostFilter::All => "all",
+        }
+    }
+
+    fn next(&self) -> Self {
+        match self {
+            HostFilter::Guests => HostFilter::Templates,
+            HostFilter::Templates => HostFilter::All,
+            HostFilter::All => HostFilter::Guests,
+        }
+    }
+
+    fn matches(&self, host: &Host) -> bool {
+        match self {
+            HostFilter::Guests => !host.template,
+            HostFilter::Templates => host.template,
+            HostFilter::All => true,
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum SortDirection {
     Ascending,
@@ -32,17 +91,159 @@ pub enum SortDirection {
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum SetupField {
+    Method,
     Name,
     Host,
     Port,
+    Endpoints,
+    Proxy,
     ApiTokenId,
     ApiTokenSecret,
+    Username,
+    Realm,
+    Password,
+    Totp,
+    Role,
     VerifySsl,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SnapshotField {
+    Name,
+    Description,
+    Vmstate,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CloneField {
+    Node,
+    Vmid,
+    Name,
+    Mode,
+    Storage,
+    Pool,
+    CiUser,
+    SshKeys,
+    IpConfig,
+    Start,
+}
+
+/// Steps of the new container wizard
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub enum ContainerStep {
+    Placement,
+    System,
+    Resources,
+    Network,
+    Review,
+}
+
+impl ContainerStep {
+    pub const COUNT: usize = 5;
+
+    pub fn number(&self) -> usize {
+        *self as usize + 1
+    }
+
+    pub fn title(&self) -> &str {
+        match self {
+            ContainerStep::Placement => "Node and OS template",
+            ContainerStep::System => "Hostname and access",
+            ContainerStep::Resources => "CPU, memory and disk",
+            ContainerStep::Network => "Network",
+            ContainerStep::Review => "Review",
+        }
+    }
+
+    fn next(&self) -> Self {
+        match self {
+            ContainerStep::Placement => ContainerStep::System,
+            ContainerStep::System => ContainerStep::Resources,
+            ContainerStep::Resources => ContainerStep::Network,
+            ContainerStep::Network | ContainerStep::Review => ContainerStep::Review,
+        }
+    }
+
+    fn previous(&self) -> Option<Self> {
+        match self {
+            ContainerStep::Placement => None,
+            ContainerStep::System => Some(ContainerStep::Placement),
+            ContainerStep::Resources => Some(ContainerStep::System),
+            ContainerStep::Network => Some(ContainerStep::Resources),
+            ContainerStep::Review => Some(ContainerStep::Network),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ContainerField {
+    Cluster,
+    Node,
+    Template,
+    Hostname,
+    Vmid,
+    SshKey,
+    Cores,
+    Memory,
+    DiskSize,
+    Storage,
+    Bridge,
+    StaticIp,
+    Address,
+    Gateway,
+    AddOverride,
+    Start,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BackupField {
+    Storage,
+    Mode,
+    Compression,
+}
+
+/// How the Setup form gets an API token
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SetupMethod {
+    // Paste a token created in the web UI
+    ExistingToken,
+    // Log in with a password and let proxmon create the token
+    CreateToken,
+}
+
+impl SetupMethod {
+    pub fn label(&self) -> &str {
+        match self {
+            SetupMethod::ExistingToken => "Use an existing API token",
+            SetupMethod::CreateToken => "Log in and create a 'proxmon' token",
+        }
+    }
+}
+
+/// Outcome of the last refresh for one configured cluster
+#[derive(Debug, Clone)]
+pub struct ClusterState {
+    pub name: String,
+    // Config entries merged into this cluster
+    pub members: Vec<String>,
+    // Endpoint that served the last successful refresh
+    pub endpoint: Option<String>,
+    // The primary endpoint did not answer and a failover endpoint was used
+    pub failed_over: bool,
+    pub status: ClusterStatus,
+    pub message: Option<String>,
+    pub host_count: usize,
+    // Privileges the cluster was read with, None if /access/permissions failed
+    pub permissions: Option<Permissions>,
+}
+
 pub struct App {
     pub hosts: Vec<Host>,
     pub selected_index: usize,
+    // Hosts marked with Space, which actions like backups apply to instead of the selection
+    pub marked: HashSet<HostId>,
+    // Category shown in the table; `selected_index` counts within it
+    pub host_filter: HostFilter,
     pub view_mode: ViewMode,
     pub is_loading: bool,
     pub last_error: Option<String>,
@@ -52,6 +253,7 @@ pub struct App {
     pub export_content: String,
     pub ip_input: Input,
     pub editing_host_name: String,
+    editing_host: Option<HostId>,
     pub sort_column: SortColumn,
     pub sort_direction: SortDirection,
     pub initial_fetch_done: bool,
@@ -61,18 +263,159 @@ pub struct App {
     pub setup_name: Input,
     pub setup_host: Input,
     pub setup_port: Input,
+    pub setup_endpoints: Input,
+    pub setup_proxy: Input,
     pub setup_token_id: Input,
     pub setup_token_secret: Input,
     pub setup_verify_ssl: bool,
+    pub setup_method: SetupMethod,
+    pub setup_username: Input,
+    pub setup_realm: Input,
+    pub setup_password: Input,
+    pub setup_totp: Input,
+    pub setup_role: TokenRole,
+    // Feedback shown inside the form, and whether a background step is running
+    pub setup_message: Option<String>,
+    pub setup_busy: bool,
+    // Host whose connection check found problems, kept so it can be saved anyway,
+    // and the field to go back to instead
+    pub setup_pending_host: Option<ProxmoxHost>,
+    pub setup_failed_field: Option<SetupField>,
+    // Certificate fingerprint shown for confirmation, and the one the user accepted
+    pub setup_offered_fingerprint: Option<String>,
+    setup_fingerprint: Option<String>,
+    // Action awaiting confirmation, the hosts it was requested for and the view
+    // to go back to afterwards
+    pub pending_action: Option<GuestAction>,
+    pub action_targets: Vec<HostId>,
+    action_return: ViewMode,
+    // Name that has to be typed to confirm an action on a protected guest
+    pub confirm_name: Option<String>,
+    pub confirm_input: Input,
+    // Background workers report back through this channel
+    event_tx: EventSender,
+    event_rx: mpsc::UnboundedReceiver<AppEvent>,
+    // Results of the refresh in flight, swapped in once every cluster answered
+    pending_hosts: Vec<Host>,
+    pub pending_clusters: Vec<ClusterState>,
+    pub refresh_total: usize,
+    // Per-cluster results of the last completed refresh
+    pub clusters: Vec<ClusterState>,
+    // Configured entries grouped by Proxmox cluster, with their (failover) clients
+    logical_clusters: Vec<LogicalCluster>,
+    // Automatic refresh schedule (see `Config::refresh_interval`)
+    pub next_refresh_at: Option<Instant>,
+    pub auto_refresh_paused: bool,
+    quiet_refresh: bool,
+    // Shown once the first refresh is done, e.g. about config file permissions
+    pub startup_warning: Option<String>,
+    // Proxmox tasks started from proxmon
+    pub tasks: TaskTracker,
+    pub task_selected: usize,
+    pub task_log_scroll: usize,
+    // Tickets of ticket-auth entries, shared with every client
+    sessions: SessionStore,
+    // Ticket logins waiting for a password (first one is being prompted), and the
+    // ones the user chose to skip
+    pub login_queue: Vec<(String, Credential)>,
+    login_skipped: Vec<(String, Credential)>,
+    pub login_password: Input,
+    pub login_totp: Input,
+    // Partial ticket while the TOTP code is asked for
+    pub login_challenge: Option<String>,
+    pub login_in_progress: bool,
+    pub login_error: Option<String>,
+    // View to go back to once the login prompt closes
+    login_return: ViewMode,
+    // Snapshot counts reused across refreshes
+    snapshot_counts: CountCache,
+    // Snapshot panel: guest it shows, its snapshots in tree order with their depth
+    snapshot_host: Option<HostId>,
+    pub snapshots: Vec<(usize, Snapshot)>,
+    pub snapshot_selected: usize,
+    pub snapshots_loading: bool,
+    pub snapshot_error: Option<String>,
+    // New snapshot form
+    pub snapshot_field: SnapshotField,
+    pub snapshot_name: Input,
+    pub snapshot_description: Input,
+    pub snapshot_vmstate: bool,
+    // Backup browser: guest it shows and its backups, newest first
+    backup_host: Option<HostId>,
+    pub backups: Vec<Backup>,
+    pub backup_selected: usize,
+    pub backups_loading: bool,
+    pub backup_error: Option<String>,
+    // Backup form: guests to back up, the node storages were listed on, and the choices
+    pub backup_targets: Vec<HostId>,
+    backup_node: Option<String>,
+    pub backup_storages: Vec<StorageInfo>,
+    pub backup_storage_index: usize,
+    pub backup_field: BackupField,
+    pub backup_mode: BackupMode,
+    pub backup_compress: Compression,
+    backup_return: ViewMode,
+    // Restore form: archive to restore and the new vmid (prefilled from /cluster/nextid)
+    pub restore_archive: Option<String>,
+    pub restore_vmid: Input,
+    // Migration target picker: guest to move and the other online nodes of its cluster
+    migrate_host: Option<HostId>,
+    pub migrate_nodes: Vec<NodeInfo>,
+    pub migrate_selected: usize,
+    pub migrate_loading: bool,
+    pub migrate_error: Option<String>,
+    // Clone form: guest to clone, online nodes of its cluster and the settings
+    clone_source: Option<HostId>,
+    pub clone_nodes: Vec<String>,
+    pub clone_node_index: usize,
+    pub clone_field: CloneField,
+    pub clone_vmid: Input,
+    pub clone_name: Input,
+    pub clone_full: bool,
+    pub clone_storage: Input,
+    pub clone_pool: Input,
+    pub clone_ciuser: Input,
+    pub clone_sshkeys: Input,
+    pub clone_ipconfig: Input,
+    pub clone_start: bool,
+    // New container wizard: cluster it creates in (None while closed), the
+    // choices loaded for it and the values entered so far
+    pub container_cluster: Option<String>,
+    pub container_step: ContainerStep,
+    pub container_field: ContainerField,
+    pub container_clusters: Vec<String>,
+    pub container_nodes: Vec<String>,
+    pub container_node_index: usize,
+    pub container_options: NodeOptions,
+    pub container_options_loading: bool,
+    pub container_template_index: usize,
+    pub container_storage_index: usize,
+    pub container_hostname: Input,
+    pub container_vmid: Input,
+    pub container_ssh_key: Input,
+    pub container_cores: Input,
+    pub container_memory: Input,
+    pub container_disk: Input,
+    pub container_bridge: Input,
+    pub container_static: bool,
+    pub container_address: Input,
+    pub container_gateway: Input,
+    pub container_add_override: bool,
+    pub container_start: bool,
+    pub container_message: Option<String>,
 }
 
 impl App {
     pub fn new(config: Config, config_path: String) -> Self {
+        let (event_tx, event_rx) = mpsc::unbounded_channel();
+
         Self {
             hosts: Vec::new(),
             selected_index: 0,
+            marked: HashSet::new(),
+            host_filter: HostFilter::Guests,
             view_mode: ViewMode::Main,
-            is_loading: true, // Start with loading state
+            is_loading: false,
             last_error: None,
             config,
             config_path,
@@ -80,6 +423,7 @@ impl App {
             export_content: String::new(),
             ip_input: Input::default(),
             editing_host_name: String::new(),
+            editing_host: None,
             sort_column: SortColumn::Name,
             sort_direction: SortDirection::Ascending,
             initial_fetch_done: false,
@@ -88,9 +432,117 @@ impl App {
             setup_name: Input::default(),
             setup_host: Input::default(),
             setup_port: Input::default().with_value("8006".to_string()),
+            setup_endpoints: Input::default(),
+            setup_proxy: Input::default(),
             setup_token_id: Input::default(),
             setup_token_secret: Input::default(),
             setup_verify_ssl: false,
+            setup_method: SetupMethod::ExistingToken,
+            setup_username: Input::default(),
+            setup_realm: Input::default().with_value("pam".to_string()),
+            setup_password: Input::default(),
+            setup_totp: Input::default(),
+            setup_role: TokenRole::ReadOnly,
+            setup_message: None,
+            setup_busy: false,
+            setup_pending_host: None,
+            setup_failed_field: None,
+            setup_offered_fingerprint: None,
+            setup_fingerprint: None,
+            pending_action: None,
+            action_targets: Vec::new(),
+            action_return: ViewMode::Main,
+            confirm_name: None,
+            confirm_input: Input::default(),
+            event_tx,
+            event_rx,
+            pending_hosts: Vec::new(),
+            pending_clusters: Vec::new(),
+            refresh_total: 0,
+            clusters: Vec::new(),
+            logical_clusters: Vec::new(),
+            next_refresh_at: None,
+            auto_refresh_paused: false,
+            quiet_refresh: false,
+            startup_warning: None,
+            tasks: TaskTracker::default(),
+            task_selected: 0,
+            task_log_scroll: 0,
+            sessions: SessionStore::default(),
+            login_queue: Vec::new(),
+            login_skipped: Vec::new(),
+            login_password: Input::default(),
+            login_totp: Input::default(),
+            login_challenge: None,
+            login_in_progress: false,
+            login_error: None,
+            login_return: ViewMode::Main,
+            snapshot_counts: CountCache::default(),
+            snapshot_host: None,
+            snapshots: Vec::new(),
+            snapshot_selected: 0,
+            snapshots_loading: false,
+            snapshot_error: None,
+            snapshot_field: SnapshotField::Name,
+            snapshot_name: Input::default(),
+            snapshot_description: Input::default(),
+            snapshot_vmstate: false,
+            backup_host: None,
+            backups: Vec::new(),
+            backup_selected: 0,
+            backups_loading: false,
+            backup_error: None,
+            backup_targets: Vec::new(),
+            backup_node: None,
+            backup_storages: Vec::new(),
+            backup_storage_index: 0,
+            backup_field: BackupField::Storage,
+            backup_mode: BackupMode::Snapshot,
+            backup_compress: Compression::Zstd,
+            backup_return: ViewMode::Main,
+            restore_archive: None,
+            restore_vmid: Input::default(),
+            migrate_host: None,
+            migrate_nodes: Vec::new(),
+            migrate_selected: 0,
+            migrate_loading: false,
+            migrate_error: None,
+            clone_source: None,
+            clone_nodes: Vec::new(),
+            clone_node_index: 0,
+            clone_field: CloneField::Node,
+            clone_vmid: Input::default(),
+            clone_name: Input::default(),
+            clone_full: true,
+            clone_storage: Input::default(),
+            clone_pool: Input::default(),
+            clone_ciuser: Input::default(),
+            clone_sshkeys: Input::default(),
+            clone_ipconfig: Input::default(),
+            clone_start: true,
+            container_cluster: None,
+            container_step: ContainerStep::Placement,
+            container_field: ContainerField::Node,
+            container_clusters: Vec::new(),
+            container_nodes: Vec::new(),
+            container_node_index: 0,
+            container_options: NodeOptions::default(),
+            container_options_loading: false,
+            container_template_index: 0,
+            container_storage_index: 0,
+            container_hostname: Input::default(),
+            container_vmid: Input::default(),
+            container_ssh_key: Input::default(),
+            container_cores: Input::default(),
+            container_memory: Input::default(),
+            container_disk: Input::default(),
+            container_bridge: Input::default(),
+            container_static: false,
+            container_address: Input::default(),
+            container_gateway: Input::default(),
+            container_add_override: true,
+            container_start: true,
+            container_message: None,
         }
     }
 
@@ -98,98 +550,325 @@ impl App {
         self.loading_frame = self.loading_frame.wrapping_add(1);
     }
 
-    pub async fn fetch_all_hosts(&mut self) -> Result<()> {
+    /// Start a background refresh of all clusters. The current host list stays
+    /// visible (and usable) until every cluster has answered. Returns false if no
+    /// refresh was started (one is running, or a login prompt comes first).
+    pub fn start_refresh(&mut self) -> bool {
+        if self.is_loading || self.view_mode == ViewMode::Login {
+            return false;
+        }
+
+        // Ask for passwords first; the refresh starts once the prompt closes
+        let missing = self.missing_logins();
+        if !missing.is_empty() {
+            self.start_login(missing);
+            return false;
+        }
+
         self.is_loading = true;
-        self.last_error = None;
+        self.quiet_refresh = false;
+        self.pending_hosts.clear();
+        self.pending_clusters.clear();
+        self.refresh_total = self.config.proxmox_hosts.len();
+        // Endpoints that answered last time are tried first
+        let preferred = self
+            .logical_clusters
+            .iter()
+            .map(|c| c.client.active_endpoint().to_string())
+            .collect();
+        spawn_refresh(
+            self.config.proxmox_hosts.clone(),
+            preferred,
+            self.sessions.clone(),
+            self.snapshot_counts.clone(),
+            self.event_tx.clone(),
+        );
+        true
+    }
 
-        let mut all_hosts = Vec::new();
+    /// Start an automatic refresh once the configured interval has elapsed
+    pub fn tick_auto_refresh(&mut self) {
+        if self.auto_refresh_paused || self.is_loading {
+            return;
+        }
 
-        // Fetch from each Proxmox host
-        for pve_host in &self.config.proxmox_hosts {
-            match ProxmoxClient::new(pve_host) {
-                Ok(client) => {
-                    match client.fetch_all_hosts().await {
-                        Ok(hosts) => {
-                            all_hosts.extend(hosts);
-                        }
-                        Err(e) => {
-                            self.last_error = Some(format!("Error fetching from {}: {}", pve_host.name, e));
-                        }
+        if let Some(due) = self.next_refresh_at {
+            // Only a refresh that actually started is a quiet one
+            if Instant::now() >= due && self.start_refresh() {
+                self.quiet_refresh = true;
+            }
+        }
+    }
+
+    pub fn toggle_auto_refresh(&mut self) {
+        if self.config.refresh_interval == 0 {
+            self.last_error = Some("Auto refresh is disabled (set refresh_interval in config)".to_string());
+            return;
+        }
+
+        self.auto_refresh_paused = !self.auto_refresh_paused;
+        if !self.auto_refresh_paused {
+            // Resume with a full interval rather than refreshing immediately
+            self.schedule_auto_refresh();
+        }
+    }
+
+    /// Seconds until the next automatic refresh, if one is scheduled
+    pub fn auto_refresh_countdown(&self) -> Option<u64> {
+        self.next_refresh_at
+            .map(|due| due.saturating_duration_since(Instant::now()).as_secs())
+    }
+
+    fn schedule_auto_refresh(&mut self) {
+        self.next_refresh_at = match self.config.refresh_interval {
+            0 => None,
+            secs => Some(Instant::now() + Duration::from_secs(secs)),
+        };
+    }
+
+    /// Apply everything background workers have reported since the last call
+    pub fn process_events(&mut self) {
+        while let Ok(event) = self.event_rx.try_recv() {
+            self.handle_event(event);
+        }
+    }
+
+    fn handle_event(&mut self, event: AppEvent) {
+        match event {
+            AppEvent::ClustersResolved { clusters, total } => {
+                self.remember_membership(&clusters);
+                self.logical_clusters = clusters;
+                self.refresh_total = total;
+            }
+            AppEvent::HostsLoaded { cluster, hosts, permissions } => {
+                self.pending_clusters.push(ClusterState {
+                    members: self.cluster_members(&cluster),
+                    endpoint: self
+                        .logical_clusters
+                        .iter()
+                        .find(|c| c.name == cluster)
+                        .map(|c| c.client.active_endpoint().to_string()),
+                    failed_over: self
+                        .logical_clusters
+                        .iter()
+                        .any(|c| c.name == cluster && c.client.is_failed_over()),
+                    name: cluster,
+                    status: ClusterStatus::Ok,
+                    message: None,
+                    host_count: hosts.len(),
+                    permissions,
+                });
+                // Cached counts keep the time they were taken
+                for host in &hosts {
+                    if let (Some(count), None) = (host.snapshots, self.snapshot_counts.get(&host.id())) {
+                        self.snapshot_counts.insert(host.id(), count);
                     }
                 }
-                Err(e) => {
-                    self.last_error = Some(format!("Error creating client for {}: {}", pve_host.name, e));
+                self.pending_hosts.extend(hosts);
+            }
+            AppEvent::ClusterFailed { cluster, status, error } => {
+                self.pending_clusters.push(ClusterState {
+                    members: self.cluster_members(&cluster),
+                    endpoint: None,
+                    failed_over: false,
+                    name: cluster,
+                    status,
+                    message: Some(error),
+                    host_count: 0,
+                    permissions: None,
+                });
+            }
+            AppEvent::RefreshFinished => {
+                self.finish_refresh();
+            }
+            AppEvent::BackupsLoaded { host, result } => {
+                self.apply_backups(host, result);
+            }
+            AppEvent::BackupStoragesLoaded { node, result } => {
+                self.apply_backup_storages(node, result);
+            }
+            AppEvent::NodesLoaded { form, cluster, result } => match form {
+                Form::Migrate => self.apply_migrate_nodes(cluster, result),
+                Form::Clone => self.apply_clone_nodes(cluster, result),
+                Form::NewContainer => self.apply_container_nodes(cluster, result),
+                // The restore form only asks for a vmid
+                Form::Restore => {}
+            },
+            AppEvent::ContainerOptionsLoaded { node, result } => {
+                self.apply_container_options(node, result);
+            }
+            AppEvent::ContainerCreated(outcome) => {
+                self.apply_container_created(outcome);
+            }
+            AppEvent::NextVmidLoaded { form, cluster, result } => {
+                self.apply_next_vmid(form, cluster, result);
+            }
+            AppEvent::SnapshotsLoaded { host, result } => {
+                self.apply_snapshots(host, result);
+            }
+            AppEvent::LoginFinished { cluster, credential, result } => {
+                self.apply_login_result((cluster, credential), result);
+            }
+            AppEvent::TokenCreated(result) => {
+                self.apply_token_created(result);
+            }
+            AppEvent::SetupChecked { host, result } => {
+                self.apply_setup_check(host, result);
+            }
+            AppEvent::SetupSecretResolved(host) => {
+                self.apply_setup_secret(host);
+            }
+            AppEvent::SetupFingerprint(result) => {
+                self.apply_setup_fingerprint(result);
+            }
+            AppEvent::Task(update) => {
+                // New tasks go in front, so keep the selection (and an open log) on the same task
+                if matches!(update, TaskUpdate::Started(_)) && !self.tasks.tasks.is_empty() {
+                    self.task_selected += 1;
                 }
+                self.tasks.apply(update);
+            }
+            AppEvent::ActionFinished(outcome) => {
+                self.apply_action_outcome(outcome);
             }
         }
+    }
+
+    fn finish_refresh(&mut self) {
+        let mut all_hosts = std::mem::take(&mut self.pending_hosts);
+
+        // Remember the selected host so the cursor stays on it after the swap
+        let selected = self.selected_host().map(|h| h.id());
 
         // Apply IP overrides
         for host in &mut all_hosts {
-            if let Some(override_entry) = self.config.ip_overrides.iter().find(|o| o.name == host.name) {
+            if let Some(override_entry) = self.config.ip_override_for(&host.id(), &host.name) {
                 host.ip = Some(override_entry.ip.clone());
             }
         }
 
         // Add manual hosts
         for manual_host in &self.config.manual_hosts {
-            all_hosts.push(Host {
-                name: manual_host.name.clone(),
-                host_type: HostType::Physical,
-                status: "unknown".to_string(),
-                ip: Some(manual_host.ip.clone()),
-                node: None,
-                vmid: None,
-                ansible_user: Some(manual_host.ansible_user.clone()),
-            });
+            all_hosts.push(Host::from_manual(manual_host));
         }
 
         self.hosts = all_hosts;
         self.is_loading = false;
+        self.initial_fetch_done = true;
+
+        // Keep clusters in config order so the panel doesn't jump around
+        let previous_summary = self.cluster_summary();
+        let mut clusters = std::mem::take(&mut self.pending_clusters);
+        clusters.sort_by_key(|c| self.config.proxmox_hosts.iter().position(|h| h.name == c.name));
+        self.clusters = clusters;
 
-        // Apply current sort
+        // Automatic refreshes only touch the status bar when cluster health changes
+        let summary = self.cluster_summary();
+        if !self.quiet_refresh || summary.is_some() || self.last_error == previous_summary {
+            self.last_error = summary;
+        }
+        if self.last_error.is_none() {
+            self.last_error = self.startup_warning.take();
+        }
+
+        // Apply current sort, then put the cursor back on the same host
         self.apply_sort();
+        self.select_host(selected.as_ref());
 
         // Reset selection if out of bounds
-        if self.selected_index >= self.hosts.len() && !self.hosts.is_empty() {
-            self.selected_index = self.hosts.len() - 1;
+        let visible = self.visible_count();
+        if self.selected_index >= visible && visible > 0 {
+            self.selected_index = visible - 1;
         }
 
-        Ok(())
+        self.schedule_auto_refresh();
+    }
+
+    fn cluster_members(&self, cluster: &str) -> Vec<String> {
+        self.logical_clusters
+            .iter()
+            .find(|c| c.name == cluster)
+            .map(|c| c.members.clone())
+            .unwrap_or_else(|| vec![cluster.to_string()])
+    }
+
+    /// One-line summary of failed clusters for the status bar, if any failed
+    pub fn cluster_summary(&self) -> Option<String> {
+        let failed: Vec<String> = self
+            .clusters
+            .iter()
+            .filter(|c| c.status != ClusterStatus::Ok)
+            .map(|c| format!("{}: {}", c.name, c.status.as_str()))
+            .collect();
+
+        if failed.is_empty() {
+            return None;
+        }
+
+        Some(format!(
+            "{}/{} clusters OK - {} (press c for details)",
+            self.clusters.len() - failed.len(),
+            self.clusters.len(),
+            failed.join(", ")
+        ))
     }
 
     pub fn export_ansible_format(&mut self) {
-        self.export_content = ansible::generate_ansible_hosts(&self.hosts, &self.config.ansible_defaults);
+        // Templates can't run, so they have no place in an inventory
+        let hosts: Vec<Host> = self.hosts.iter().filter(|h| !h.template).cloned().collect();
+        self.export_content = ansible::generate_ansible_hosts(&hosts, &self.config.ansible_defaults);
         self.view_mode = ViewMode::Export;
     }
 
+    /// Hosts of the current category, in table order
+    pub fn visible_hosts(&self) -> impl Iterator<Item = &Host> {
+        self.hosts.iter().filter(|h| self.host_filter.matches(h))
+    }
+
+    fn visible_count(&self) -> usize {
+        self.visible_hosts().count()
+    }
+
+    /// Switch between guests, templates and everything, keeping the cursor on
+    /// the selected host if it is still listed
+    pub fn cycle_host_filter(&mut self) {
+        let selected = self.selected_host().map(|h| h.id());
+        self.host_filter = self.host_filter.next();
+        self.selected_index = 0;
+        self.select_host(selected.as_ref());
+    }
+
     pub fn next(&mut self) {
-        if self.hosts.is_empty() {
+        let count = self.visible_count();
+        if count == 0 {
             return;
         }
-        self.selected_index = (self.selected_index + 1) % self.hosts.len();
+        self.selected_index = (self.selected_index + 1) % count;
     }
 
     pub fn previous(&mut self) {
-        if self.hosts.is_empty() {
+        let count = self.visible_count();
+        if count == 0 {
             return;
         }
         if self.selected_index == 0 {
-            self.selected_index = self.hosts.len() - 1;
+            self.selected_index = count - 1;
         } else {
             self.selected_index -= 1;
         }
     }
 
     pub fn page_down(&mut self) {
-        if self.hosts.is_empty() {
+        let count = self.visible_count();
+        if count == 0 {
             return;
         }
         // Jump by 10 items or to the end
-        self.selected_index = (self.selected_index + 10).min(self.hosts.len() - 1);
+        self.selected_index = (self.selected_index + 10).min(count - 1);
     }
 
     pub fn page_up(&mut self) {
-        if self.hosts.is_empty() {
+        if self.visible_count() == 0 {
             return;
         }
         // Jump by 10 items or to the start
@@ -201,167 +880,1893 @@ impl App {
     }
 
     pub fn go_to_top(&mut self) {
-        if !self.hosts.is_empty() {
+        if self.visible_count() > 0 {
             self.selected_index = 0;
         }
     }
 
     pub fn go_to_bottom(&mut self) {
-        if !self.hosts.is_empty() {
-            self.selected_index = self.hosts.len() - 1;
+        let count = self.visible_count();
+        if count > 0 {
+            self.selected_index = count - 1;
         }
     }
 
-    pub fn copy_to_clipboard(&mut self) {
-        if let Ok(mut clipboard) = Clipboard::new() {
-            if let Err(e) = clipboard.set_text(&self.export_content) {
-                self.last_error = Some(format!("Failed to copy to clipboard: {}", e));
-            } else {
-                self.last_error = Some("Copied to clipboard!".to_string());
-            }
-        } else {
-            self.last_error = Some("Failed to access clipboard".to_string());
+    pub fn selected_host(&self) -> Option<&Host> {
+        self.visible_hosts().nth(self.selected_index)
+    }
+
+    pub fn host_by_id(&self, id: &HostId) -> Option<&Host> {
+        self.hosts.iter().find(|h| h.id() == *id)
+    }
+
+    /// Move the cursor to the given host, if it is still in the list
+    fn select_host(&mut self, id: Option<&HostId>) {
+        if let Some(index) = id.and_then(|id| self.visible_hosts().position(|h| h.id() == *id)) {
+            self.selected_index = index;
         }
     }
 
-    pub fn start_edit_ip(&mut self) {
-        if self.hosts.is_empty() {
-            return;
+    /// Whether the credentials used for actions on `host` have `privilege`.
+    /// Privileges that aren't known (unreadable, or separate operator
+    /// credentials) count as granted and are left for Proxmox to check.
+    pub fn allows(&self, host: &Host, privilege: &str) -> bool {
+        let (Some(cluster), Some(vmid)) = (&host.cluster, host.vmid) else {
+            return true;
+        };
+        self.cluster_allows(cluster, vmid, host.pool.as_deref(), privilege)
+    }
+
+    /// Like `allows`, for a vmid that may not exist yet
+    fn cluster_allows(&self, cluster: &str, vmid: u32, pool: Option<&str>, privilege: &str) -> bool {
+        self.permissions_allow(cluster, |p| p.guest_has(vmid, pool, privilege))
+    }
+
+    /// Whether the new container wizard can create anything, for greying out its key
+    pub fn can_create_guest(&self) -> bool {
+        self.container_default_cluster()
+            .is_none_or(|cluster| self.permissions_allow(&cluster, |p| p.new_guest_has(permissions::VM_ALLOCATE)))
+    }
+
+    fn permissions_allow(&self, cluster: &str, check: impl Fn(&Permissions) -> bool) -> bool {
+        if self.logical_clusters.iter().any(|c| c.name == cluster && c.client.has_operator()) {
+            return true;
         }
+        self.clusters
+            .iter()
+            .find(|c| c.name == cluster)
+            .and_then(|c| c.permissions.as_ref())
+            .is_none_or(check)
+    }
 
-        let host = &self.hosts[self.selected_index];
-        self.editing_host_name = host.name.clone();
+    fn client_for(&self, cluster: &str) -> Result<ProxmoxClient> {
+        // Prefer the merged client from the last refresh so actions can fail over too
+        if let Some(logical) = self.logical_clusters.iter().find(|c| c.name == cluster) {
+            return Ok(logical.client.clone());
+        }
 
-        // Pre-fill with existing IP if available
-        let current_ip = host.ip.clone().unwrap_or_default();
-        self.ip_input = Input::default().with_value(current_ip);
+        let pve_host = self
+            .config
+            .proxmox_hosts
+            .iter()
+            .find(|h| h.name == cluster)
+            .with_context(|| format!("Proxmox host '{}' is no longer configured", cluster))?;
+        ProxmoxClient::new(pve_host, &self.sessions)
+    }
 
-        self.view_mode = ViewMode::EditIp;
+    /// Ticket-auth entries without a valid ticket that the user hasn't skipped.
+    /// Operator logins are left out, those are asked for when they are needed.
+    fn missing_logins(&self) -> Vec<(String, Credential)> {
+        self.config
+            .proxmox_hosts
+            .iter()
+            .filter(|h| h.auth == AuthMode::Ticket)
+            .map(|h| (h.name.clone(), Credential::Viewer))
+            .filter(|login| !self.sessions.is_logged_in(&login.0) && !self.login_skipped.contains(login))
+            .collect()
     }
 
-    pub fn save_ip_override(&mut self) -> Result<()> {
-        let new_ip = self.ip_input.value().trim().to_string();
+    fn start_login(&mut self, entries: Vec<(String, Credential)>) {
+        if self.view_mode != ViewMode::Login {
+            self.login_return = self.view_mode.clone();
+        }
+        self.login_queue = entries;
+        self.reset_login_prompt();
+        self.view_mode = ViewMode::Login;
+    }
 
-        if new_ip.is_empty() {
-            // Remove the override if IP is empty
-            self.config.ip_overrides.retain(|o| o.name != self.editing_host_name);
+    /// Prompt again for every ticket-auth entry that isn't logged in, including skipped ones
+    pub fn relogin(&mut self) {
+        self.login_skipped.clear();
+        let missing = self.missing_logins();
+        if missing.is_empty() {
+            self.last_error = Some("No hosts waiting for a login".to_string());
+            return;
+        }
+        self.start_login(missing);
+    }
 
-            // Also remove from the in-memory host list
-            if let Some(host) = self.hosts.iter_mut().find(|h| h.name == self.editing_host_name) {
-                host.ip = None;
+    fn reset_login_prompt(&mut self) {
+        self.login_password = Input::default();
+        self.login_totp = Input::default();
+        self.login_challenge = None;
+        self.login_in_progress = false;
+        self.login_error = None;
+    }
+
+    /// Send the password (or TOTP code) for the entry at the front of the queue
+    pub fn submit_login(&mut self) {
+        if self.login_in_progress {
+            return;
+        }
+        let Some((entry, credential)) = self.login_queue.first().cloned() else {
+            return;
+        };
+        let client = match self.client_for(&entry) {
+            Ok(client) => client,
+            Err(e) => {
+                self.login_error = Some(format!("{:#}", e));
+                return;
             }
-        } else {
-            // Add or update the override
-            if let Some(existing) = self.config.ip_overrides.iter_mut().find(|o| o.name == self.editing_host_name) {
-                existing.ip = new_ip.clone();
-            } else {
-                self.config.ip_overrides.push(IpOverride {
-                    name: self.editing_host_name.clone(),
-                    ip: new_ip.clone(),
-                });
+        };
+
+        // Drop the password from the input as soon as it has been handed off
+        let password = std::mem::take(&mut self.login_password).value().to_string();
+        let code = std::mem::take(&mut self.login_totp).value().to_string();
+        let challenge = self.login_challenge.clone();
+
+        self.login_in_progress = true;
+        self.login_error = None;
+        let tx = self.event_tx.clone();
+        tokio::spawn(async move {
+            let result = match challenge {
+                Some(challenge) => client.login_totp(credential, &challenge, &code).await,
+                None => client.login(credential, &password).await,
+            };
+            let _ = tx.send(AppEvent::LoginFinished { cluster: entry, credential, result });
+        });
+    }
+
+    fn apply_login_result(&mut self, login: (String, Credential), result: Result<LoginStep>) {
+        if self.login_queue.first() != Some(&login) {
+            return;
+        }
+        self.login_in_progress = false;
+
+        match result {
+            // The client already stored the ticket in the session store
+            Ok(LoginStep::Done(_)) => self.next_login(),
+            Ok(LoginStep::NeedTotp { challenge }) => {
+                self.login_challenge = Some(challenge);
             }
+            Err(e) => {
+                // Start over from the password after a failed attempt
+                self.login_challenge = None;
+                self.login_error = Some(format!("{:#}", e));
+            }
+        }
+    }
 
-            // Immediately update the in-memory host list (instant UI update!)
-            if let Some(host) = self.hosts.iter_mut().find(|h| h.name == self.editing_host_name) {
-                host.ip = Some(new_ip);
+    /// Leave the entry at the front of the queue logged out for now
+    pub fn skip_login(&mut self) {
+        if let Some(login) = self.login_queue.first().cloned() {
+            // Without the operator login the action that asked for it can't run
+            if login.1 == Credential::Operator {
+                self.cancel_action();
+                self.login_return = self.action_return.clone();
             }
+            self.login_skipped.push(login);
         }
+        self.next_login();
+    }
 
-        // Save to file
-        self.save_config()?;
+    /// Ask for the operator passwords of `clusters` before a mutating action if
+    /// they use an operator login without a valid ticket. The current view
+    /// (usually the confirmation) comes back once the logins are done.
+    fn require_operator_login(&mut self, clusters: &[String]) {
+        let missing: Vec<(String, Credential)> = clusters
+            .iter()
+            .filter(|cluster| {
+                self.client_for(cluster)
+                    .is_ok_and(|client| !client.is_logged_in(Credential::Operator))
+            })
+            .map(|cluster| (cluster.clone(), Credential::Operator))
+            .collect();
+        if !missing.is_empty() {
+            self.start_login(missing);
+        }
+    }
 
-        self.last_error = Some(format!("IP saved for {}", self.editing_host_name));
-        self.view_mode = ViewMode::Main;
-        // No need for needs_refresh - we updated in-memory immediately!
+    fn next_login(&mut self) {
+        if !self.login_queue.is_empty() {
+            self.login_queue.remove(0);
+        }
+        self.reset_login_prompt();
 
-        Ok(())
+        if self.login_queue.is_empty() {
+            self.view_mode = std::mem::replace(&mut self.login_return, ViewMode::Main);
+            self.start_refresh();
+        }
     }
 
-    fn save_config(&self) -> Result<()> {
-        // Ensure parent directory exists
-        if let Some(parent) = std::path::Path::new(&self.config_path).parent() {
-            std::fs::create_dir_all(parent)?;
-        }
+    pub fn request_power_action(&mut self, action: PowerAction) {
+        let Some(host) = self.selected_host().cloned() else {
+            return;
+        };
+        self.request_action(&host, GuestAction::Power(action), ViewMode::Main);
+    }
 
-        let yaml = serde_yaml::to_string(&self.config)?;
-        std::fs::write(&self.config_path, yaml)?;
-        Ok(())
+    /// Check that `action` may run on `host` and ask for confirmation, coming
+    /// back to `return_to` afterwards
+    fn request_action(&mut self, host: &Host, action: GuestAction, return_to: ViewMode) {
+        self.request_action_for(std::slice::from_ref(host), action, return_to);
     }
 
-    pub fn start_setup(&mut self) {
-        // Reset form fields
-        self.setup_name = Input::default();
-        self.setup_host = Input::default();
-        self.setup_port = Input::default().with_value("8006".to_string());
-        self.setup_token_id = Input::default();
-        self.setup_token_secret = Input::default();
-        self.setup_verify_ssl = false;
-        self.setup_field = SetupField::Name;
-        self.view_mode = ViewMode::Setup;
+    /// Like `request_action`, for several guests at once. Nothing runs unless
+    /// the action is allowed on all of them.
+    fn request_action_for(&mut self, hosts: &[Host], action: GuestAction, return_to: ViewMode) {
+        if proxmox::is_read_only() {
+            self.last_error = Some(format!("Read-only mode: {} is disabled", action.label()));
+            return;
+        }
+        let mut confirm_name = None;
+        for host in hosts {
+            if host.vmid.is_none() || host.node.is_none() || host.cluster.is_none() {
+                self.last_error = Some(format!("{} is not a Proxmox guest", host.name));
+                return;
+            }
+            if !self.allows(host, action.privilege()) {
+                self.last_error = Some(format!("No permission for {} on {} (needs {})", action.label(), host.name, action.privilege()));
+                return;
+            }
+
+            let protection = if action.is_destructive() { self.config.protection_for(host) } else { None };
+            match protection {
+                Some(Protection::Block) => {
+                    self.last_error = Some(format!("{} is protected: {} is blocked", host.name, action.label()));
+                    return;
+                }
+                Some(Protection::Confirm) if confirm_name.is_none() => confirm_name = Some(host.name.clone()),
+                _ => {}
+            }
+        }
+
+        let mut clusters: Vec<String> = hosts.iter().filter_map(|h| h.cluster.clone()).collect();
+        clusters.sort();
+        clusters.dedup();
+
+        self.action_targets = hosts.iter().map(|h| h.id()).collect();
+        self.confirm_name = confirm_name;
+        self.confirm_input = Input::default();
+        self.pending_action = Some(action);
+        self.action_return = return_to;
+        self.view_mode = ViewMode::ConfirmAction;
+        self.require_operator_login(&clusters);
     }
 
-    pub fn next_setup_field(&mut self) {
-        self.setup_field = match self.setup_field {
-            SetupField::Name => SetupField::Host,
-            SetupField::Host => SetupField::Port,
-            SetupField::Port => SetupField::ApiTokenId,
-            SetupField::ApiTokenId => SetupField::ApiTokenSecret,
-            SetupField::ApiTokenSecret => SetupField::VerifySsl,
-            SetupField::VerifySsl => SetupField::Name,
+    pub fn confirm_action(&mut self) {
+        // Protected guests need their name typed, a stray keypress isn't enough
+        if let Some(name) = &self.confirm_name {
+            if self.confirm_input.value() != name {
+                return;
+            }
+        }
+        self.confirm_name = None;
+        self.view_mode = self.action_return.clone();
+
+        let Some(action) = self.pending_action.take() else {
+            return;
         };
+        let targets: Vec<Host> = std::mem::take(&mut self.action_targets)
+            .iter()
+            .filter_map(|id| self.host_by_id(id).cloned())
+            .collect();
+        if let [host] = targets.as_slice() {
+            self.last_error = Some(format!("{} requested for {}...", action.label(), host.name));
+        } else {
+            self.last_error = Some(format!("{} requested for {} guests...", action.label(), targets.len()));
+        }
+        // Acting on the marked hosts uses the marks up
+        if targets.len() > 1 {
+            self.marked.clear();
+        }
+
+        for host in targets {
+            self.spawn_action(host, action.clone());
+        }
     }
 
-    pub fn prev_setup_field(&mut self) {
-        self.setup_field = match self.setup_field {
-            SetupField::Name => SetupField::VerifySsl,
-            SetupField::Host => SetupField::Name,
-            SetupField::Port => SetupField::Host,
-            SetupField::ApiTokenId => SetupField::Port,
-            SetupField::ApiTokenSecret => SetupField::ApiTokenId,
-            SetupField::VerifySsl => SetupField::ApiTokenSecret,
+    /// Run `action` on `host` in the background, following its Proxmox task
+    fn spawn_action(&mut self, host: Host, action: GuestAction) {
+        let (Some(cluster), Some(node), Some(vmid)) = (host.cluster.clone(), host.node.clone(), host.vmid) else {
+            return;
         };
-    }
 
-    pub fn save_proxmox_host(&mut self) -> Result<()> {
-        use crate::config::ProxmoxHost;
+        let client = match self.client_for(&cluster) {
+            Ok(client) => client,
+            Err(e) => {
+                self.last_error = Some(format!("{} failed: {}", action.label(), e));
+                return;
+            }
+        };
 
-        // Validate fields
-        let name = self.setup_name.value().trim();
-        let host = self.setup_host.value().trim();
-        let port_str = self.setup_port.value().trim();
-        let token_id = self.setup_token_id.value().trim();
-        let token_secret = self.setup_token_secret.value().trim();
+        // Run the action in the background so the UI stays responsive while Proxmox works
+        let tx = self.event_tx.clone();
+        let tasks = TaskHandle::new(self.event_tx.clone());
+        tokio::spawn(async move {
+            let result = async {
+                let upid = client.run_action(&node, &host.host_type, vmid, &action).await?;
+                let description = format!("{} {}", action.label(), host.name);
+                tasks.follow(&client, &cluster, &upid, description).await?;
+                // A clone gets its cloud-init settings before it starts for the first time
+                if let GuestAction::Clone(spec) = &action {
+                    client.configure_clone(&host.host_type, spec).await?;
+                    if spec.start {
+                        let upid = client.guest_power(&spec.target, &host.host_type, spec.vmid, PowerAction::Start).await?;
+                        let description = format!("Start clone {}", spec.vmid);
+                        tasks.follow(&client, &cluster, &upid, description).await?;
+                    }
+                }
+                // A rollback can stop the guest, so the status is worth re-reading after any action
+                client.guest_status(action.node_after(&node), &host.host_type, vmid).await
+            }
+            .await;
 
-        if name.is_empty() || host.is_empty() || token_id.is_empty() || token_secret.is_empty() {
-            self.last_error = Some("All fields except port are required".to_string());
-            return Ok(());
+            let _ = tx.send(AppEvent::ActionFinished(ActionOutcome {
+                host: host.id(),
+                host_name: host.name,
+                action,
+                result,
+            }));
+        });
+    }
+
+    pub fn cancel_action(&mut self) {
+        self.pending_action = None;
+        self.action_targets.clear();
+        self.confirm_name = None;
+        self.view_mode = self.action_return.clone();
+    }
+
+    fn apply_action_outcome(&mut self, outcome: ActionOutcome) {
+        match outcome.result {
+            Ok(status) => {
+                if let Some(host) = self.hosts.iter_mut().find(|h| h.id() == outcome.host) {
+                    host.status = status.clone();
+                    // A migrated guest moves to its new node right away
+                    if let Some(node) = &host.node {
+                        host.node = Some(outcome.action.node_after(node).to_string());
+                    }
+                }
+                if matches!(outcome.action, GuestAction::Migrate { .. }) {
+                    self.apply_sort();
+                }
+                self.last_error = Some(format!("{} of {} finished ({})", outcome.action.label(), outcome.host_name, status));
+            }
+            Err(e) => {
+                self.last_error = Some(format!("{} of {} failed: {}", outcome.action.label(), outcome.host_name, e));
+            }
+        }
+
+        // Snapshots changed (or a failed action left them in an unknown state)
+        if outcome.action.is_snapshot() {
+            self.snapshot_counts.invalidate(&outcome.host);
+            if self.snapshot_host.as_ref() == Some(&outcome.host) {
+                self.load_snapshots();
+            }
+        }
+        match outcome.action {
+            GuestAction::Backup { .. } if self.backup_host.as_ref() == Some(&outcome.host) => self.load_backups(),
+            // The restored or cloned guest shows up with the next inventory
+            GuestAction::Restore { .. } | GuestAction::Clone(_) => {
+                self.start_refresh();
+            }
+            _ => {}
+        }
+    }
+
+    /// Open the migration target picker for the selected guest
+    pub fn start_migrate(&mut self) {
+        let Some(host) = self.selected_host() else {
+            return;
+        };
+        let Some(cluster) = host.cluster.clone().filter(|_| host.vmid.is_some() && host.node.is_some()) else {
+            self.last_error = Some(format!("{} is not a Proxmox guest", host.name));
+            return;
+        };
+        let client = match self.client_for(&cluster) {
+            Ok(client) => client,
+            Err(e) => {
+                self.last_error = Some(format!("{:#}", e));
+                return;
+            }
+        };
+
+        self.migrate_host = Some(host.id());
+        self.migrate_nodes.clear();
+        self.migrate_selected = 0;
+        self.migrate_loading = true;
+        self.migrate_error = None;
+        self.view_mode = ViewMode::Migrate;
+
+        let tx = self.event_tx.clone();
+        tokio::spawn(async move {
+            let result = client.nodes().await;
+            let _ = tx.send(AppEvent::NodesLoaded { form: Form::Migrate, cluster, result });
+        });
+    }
+
+    fn apply_migrate_nodes(&mut self, cluster: String, result: Result<Vec<NodeInfo>>) {
+        let Some(host) = self.migrate_target().cloned() else {
+            return;
+        };
+        if host.cluster.as_ref() != Some(&cluster) {
+            return;
+        }
+        self.migrate_loading = false;
+
+        match result {
+            Ok(nodes) => {
+                let mut nodes: Vec<NodeInfo> = nodes
+                    .into_iter()
+                    .filter(|n| n.is_online() && Some(&n.node) != host.node.as_ref())
+                    .collect();
+                nodes.sort_by(|a, b| a.node.cmp(&b.node));
+                if nodes.is_empty() {
+                    self.migrate_error = Some("No other online node to migrate to".to_string());
+                }
+                self.migrate_nodes = nodes;
+            }
+            Err(e) => self.migrate_error = Some(format!("{:#}", e)),
+        }
+    }
+
+    /// Host the migration picker is open for
+    pub fn migrate_target(&self) -> Option<&Host> {
+        self.migrate_host.as_ref().and_then(|id| self.host_by_id(id))
+    }
+
+    fn submit_migrate(&mut self) {
+        let (Some(host), Some(node)) = (self.migrate_target().cloned(), self.migrate_nodes.get(self.migrate_selected)) else {
+            return;
+        };
+        let action = GuestAction::Migrate {
+            target: node.node.clone(),
+            running: host.status == "running",
+        };
+        self.migrate_host = None;
+        self.view_mode = ViewMode::Main;
+        self.request_action(&host, action, ViewMode::Main);
+    }
+
+    /// Open the clone form for the selected guest or template
+    pub fn start_clone(&mut self) {
+        let Some(host) = self.selected_host().cloned() else {
+            return;
+        };
+        let Some(cluster) = host.cluster.clone().filter(|_| host.vmid.is_some() && host.node.is_some()) else {
+            self.last_error = Some(format!("{} is not a Proxmox guest", host.name));
+            return;
+        };
+        let client = match self.client_for(&cluster) {
+            Ok(client) => client,
+            Err(e) => {
+                self.last_error = Some(format!("{:#}", e));
+                return;
+            }
+        };
+
+        self.clone_source = Some(host.id());
+        self.clone_nodes = host.node.clone().into_iter().collect();
+        self.clone_node_index = 0;
+        self.clone_field = CloneField::Node;
+        self.clone_vmid = Input::default();
+        self.clone_name = Input::default();
+        // Linked clones are only possible from templates
+        self.clone_full = !host.template;
+        self.clone_storage = Input::default();
+        self.clone_pool = Input::default().with_value(host.pool.clone().unwrap_or_default());
+        self.clone_ciuser = Input::default();
+        self.clone_sshkeys = Input::default();
+        self.clone_ipconfig = Input::default();
+        self.clone_start = true;
+        self.last_error = None;
+        self.view_mode = ViewMode::Clone;
+
+        let tx = self.event_tx.clone();
+        tokio::spawn(async move {
+            let result = client.next_vmid().await;
+            let _ = tx.send(AppEvent::NextVmidLoaded { form: Form::Clone, cluster: cluster.clone(), result });
+            let result = client.nodes().await;
+            let _ = tx.send(AppEvent::NodesLoaded { form: Form::Clone, cluster, result });
+        });
+    }
+
+    fn apply_clone_nodes(&mut self, cluster: String, result: Result<Vec<NodeInfo>>) {
+        let Some(source) = self.clone_target().cloned() else {
+            return;
+        };
+        if source.cluster.as_ref() != Some(&cluster) {
+            return;
+        }
+
+        match result {
+            Ok(nodes) => {
+                let mut nodes: Vec<String> = nodes.into_iter().filter(|n| n.is_online()).map(|n| n.node).collect();
+                nodes.sort();
+                // Start on the source's node, where linked clones have to stay without shared storage
+                self.clone_node_index = source
+                    .node
+                    .as_ref()
+                    .and_then(|node| nodes.iter().position(|n| n == node))
+                    .unwrap_or(0);
+                if !nodes.is_empty() {
+                    self.clone_nodes = nodes;
+                }
+            }
+            Err(e) => self.last_error = Some(format!("Failed to list nodes: {:#}", e)),
+        }
+    }
+
+    /// Guest the clone form is open for
+    pub fn clone_target(&self) -> Option<&Host> {
+        self.clone_source.as_ref().and_then(|id| self.host_by_id(id))
+    }
+
+    /// Fields of the clone form; containers have no cloud-init
+    pub fn clone_fields(&self) -> Vec<CloneField> {
+        let mut fields = vec![
+            CloneField::Node,
+            CloneField::Vmid,
+            CloneField::Name,
+            CloneField::Mode,
+            CloneField::Storage,
+            CloneField::Pool,
+        ];
+        if self.clone_target().is_some_and(|h| h.host_type == HostType::VM) {
+            fields.extend([CloneField::CiUser, CloneField::SshKeys, CloneField::IpConfig]);
+        }
+        fields.push(CloneField::Start);
+        fields
+    }
+
+    fn move_clone_field(&mut self, forward: bool) {
+        let fields = self.clone_fields();
+        let index = fields.iter().position(|f| *f == self.clone_field).unwrap_or(0);
+        let next = if forward { (index + 1) % fields.len() } else { (index + fields.len() - 1) % fields.len() };
+        self.clone_field = fields[next];
+    }
+
+    pub fn clone_input_mut(&mut self) -> Option<&mut Input> {
+        match self.clone_field {
+            CloneField::Vmid => Some(&mut self.clone_vmid),
+            CloneField::Name => Some(&mut self.clone_name),
+            CloneField::Storage => Some(&mut self.clone_storage),
+            CloneField::Pool => Some(&mut self.clone_pool),
+            CloneField::CiUser => Some(&mut self.clone_ciuser),
+            CloneField::SshKeys => Some(&mut self.clone_sshkeys),
+            CloneField::IpConfig => Some(&mut self.clone_ipconfig),
+            CloneField::Node | CloneField::Mode | CloneField::Start => None,
+        }
+    }
+
+    fn toggle_clone_choice(&mut self) {
+        match self.clone_field {
+            CloneField::Node if !self.clone_nodes.is_empty() => {
+                self.clone_node_index = (self.clone_node_index + 1) % self.clone_nodes.len();
+            }
+            CloneField::Mode if self.clone_target().is_some_and(|h| h.template) => {
+                self.clone_full = !self.clone_full;
+            }
+            CloneField::Mode => {
+                self.last_error = Some("Linked clones can only be made from templates".to_string());
+            }
+            CloneField::Start => self.clone_start = !self.clone_start,
+            _ => {}
+        }
+    }
+
+    fn submit_clone(&mut self) {
+        let Some(source) = self.clone_target().cloned() else {
+            return;
+        };
+        let vmid = match self.parse_new_vmid(&self.clone_vmid, source.cluster.as_ref()) {
+            Ok(vmid) => vmid,
+            Err(e) => {
+                self.last_error = Some(e);
+                return;
+            }
+        };
+        let name = self.clone_name.value().trim().to_string();
+        // Guest names have to be valid DNS names
+        if !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.') || name.starts_with(['-', '.']) {
+            self.last_error = Some("Name may only use letters, digits, - and .".to_string());
+            return;
+        }
+        let Some(target) = self.clone_nodes.get(self.clone_node_index).cloned() else {
+            return;
+        };
+
+        let optional = |input: &Input| Some(input.value().trim().to_string()).filter(|v| !v.is_empty());
+        let is_vm = source.host_type == HostType::VM;
+        let spec = CloneSpec {
+            vmid,
+            name,
+            target,
+            full: self.clone_full,
+            storage: optional(&self.clone_storage),
+            pool: optional(&self.clone_pool),
+            ciuser: optional(&self.clone_ciuser).filter(|_| is_vm),
+            sshkeys: optional(&self.clone_sshkeys).filter(|_| is_vm),
+            ipconfig0: optional(&self.clone_ipconfig).filter(|_| is_vm),
+            start: self.clone_start,
+        };
+
+        self.clone_source = None;
+        self.view_mode = ViewMode::Main;
+        self.request_action(&source, GuestAction::Clone(Box::new(spec)), ViewMode::Main);
+    }
+
+    /// Open the new container wizard, in the selected host's cluster if it has one
+    pub fn start_create_container(&mut self) {
+        if proxmox::is_read_only() {
+            self.last_error = Some("Read-only mode: creating containers is disabled".to_string());
+            return;
+        }
+        let Some(cluster) = self.container_default_cluster() else {
+            self.last_error = Some("No reachable Proxmox cluster to create a container in".to_string());
+            return;
+        };
+
+        self.container_clusters = self.logical_clusters.iter().map(|c| c.name.clone()).collect();
+        self.container_step = ContainerStep::Placement;
+        self.container_hostname = Input::default();
+        self.container_vmid = Input::default();
+        self.container_ssh_key = Input::default();
+        self.container_cores = Input::default().with_value("1".to_string());
+        self.container_memory = Input::default().with_value("512".to_string());
+        self.container_disk = Input::default().with_value("8".to_string());
+        self.container_bridge = Input::default().with_value("vmbr0".to_string());
+        self.container_static = false;
+        self.container_address = Input::default();
+        self.container_gateway = Input::default();
+        self.container_add_override = true;
+        self.container_start = true;
+        self.container_message = None;
+        self.view_mode = ViewMode::ContainerCreate;
+        self.select_container_cluster(cluster);
+        self.container_field = self.container_fields()[0];
+    }
+
+    /// Cluster the wizard opens in: the selected host's, or else the first one
+    fn container_default_cluster(&self) -> Option<String> {
+        let preferred = self.selected_host().and_then(|h| h.cluster.as_ref());
+        self.logical_clusters
+            .iter()
+            .find(|c| Some(&c.name) == preferred)
+            .or(self.logical_clusters.first())
+            .map(|c| c.name.clone())
+    }
+
+    /// Switch the wizard to `cluster` and load its nodes
+    fn select_container_cluster(&mut self, cluster: String) {
+        let client = match self.client_for(&cluster) {
+            Ok(client) => client,
+            Err(e) => {
+                self.container_message = Some(format!("{:#}", e));
+                return;
+            }
+        };
+
+        self.container_cluster = Some(cluster.clone());
+        self.container_nodes.clear();
+        self.container_node_index = 0;
+        self.container_options = NodeOptions::default();
+        self.container_vmid = Input::default();
+
+        let tx = self.event_tx.clone();
+        let form = Form::NewContainer;
+        let loading = cluster.clone();
+        tokio::spawn(async move {
+            let result = client.next_vmid().await;
+            let _ = tx.send(AppEvent::NextVmidLoaded { form, cluster: loading.clone(), result });
+            let result = client.nodes().await;
+            let _ = tx.send(AppEvent::NodesLoaded { form, cluster: loading, result });
+        });
+        self.require_operator_login(&[cluster]);
+    }
+
+    fn apply_container_nodes(&mut self, cluster: String, result: Result<Vec<NodeInfo>>) {
+        if self.container_cluster.as_ref() != Some(&cluster) {
+            return;
+        }
+        match result {
+            Ok(nodes) => {
+                let mut nodes: Vec<String> = nodes.into_iter().filter(|n| n.is_online()).map(|n| n.node).collect();
+                nodes.sort();
+                // Start on the selected guest's node, if it is in this cluster
+                let preferred = self.selected_host().filter(|h| h.cluster.as_ref() == Some(&cluster)).and_then(|h| h.node.clone());
+                self.container_node_index = preferred.and_then(|p| nodes.iter().position(|n| *n == p)).unwrap_or(0);
+                if nodes.is_empty() {
+                    self.container_message = Some(format!("No online node in {}", cluster));
+                }
+                self.container_nodes = nodes;
+                self.load_container_options();
+            }
+            Err(e) => self.container_message = Some(format!("Failed to list nodes: {:#}", e)),
+        }
+    }
+
+    /// Load the OS templates and storages of the chosen node
+    fn load_container_options(&mut self) {
+        let (Some(cluster), Some(node)) = (self.container_cluster.clone(), self.container_node().map(str::to_string)) else {
+            return;
+        };
+        let Ok(client) = self.client_for(&cluster) else {
+            return;
+        };
+
+        self.container_options = NodeOptions::default();
+        self.container_options_loading = true;
+        let tx = self.event_tx.clone();
+        tokio::spawn(async move {
+            let result = client.container_options(&node).await;
+            let _ = tx.send(AppEvent::ContainerOptionsLoaded { node, result });
+        });
+    }
+
+    fn apply_container_options(&mut self, node: String, result: Result<NodeOptions>) {
+        if self.container_cluster.is_none() || self.container_node() != Some(node.as_str()) {
+            return;
+        }
+        self.container_options_loading = false;
+
+        match result {
+            Ok(options) => {
+                if options.templates.is_empty() {
+                    self.container_message = Some(format!("No container templates on {}: download one under Storage → CT Templates", node));
+                }
+                self.container_template_index = 0;
+                // local-lvm is where a default install keeps container disks
+                self.container_storage_index = options.storages.iter().position(|s| s.storage == "local-lvm").unwrap_or(0);
+                self.container_options = options;
+            }
+            Err(e) => self.container_message = Some(format!("Failed to list templates and storages: {:#}", e)),
+        }
+    }
+
+    pub fn container_node(&self) -> Option<&str> {
+        self.container_nodes.get(self.container_node_index).map(String::as_str)
+    }
+
+    /// Fields of the current wizard step
+    pub fn container_fields(&self) -> Vec<ContainerField> {
+        match self.container_step {
+            ContainerStep::Placement if self.container_clusters.len() > 1 => {
+                vec![ContainerField::Cluster, ContainerField::Node, ContainerField::Template]
+            }
+            ContainerStep::Placement => vec![ContainerField::Node, ContainerField::Template],
+            ContainerStep::System => vec![ContainerField::Hostname, ContainerField::Vmid, ContainerField::SshKey],
+            ContainerStep::Resources => vec![
+                ContainerField::Cores,
+                ContainerField::Memory,
+                ContainerField::DiskSize,
+                ContainerField::Storage,
+            ],
+            ContainerStep::Network if self.container_static => vec![
+                ContainerField::Bridge,
+                ContainerField::StaticIp,
+                ContainerField::Address,
+                ContainerField::Gateway,
+                ContainerField::AddOverride,
+            ],
+            ContainerStep::Network => vec![ContainerField::Bridge, ContainerField::StaticIp],
+            ContainerStep::Review => vec![ContainerField::Start],
+        }
+    }
+
+    fn move_container_field(&mut self, forward: bool) {
+        let fields = self.container_fields();
+        let index = fields.iter().position(|f| *f == self.container_field).unwrap_or(0);
+        let next = if forward { (index + 1) % fields.len() } else { (index + fields.len() - 1) % fields.len() };
+        self.container_field = fields[next];
+    }
+
+    pub fn container_input_mut(&mut self) -> Option<&mut Input> {
+        match self.container_field {
+            ContainerField::Hostname => Some(&mut self.container_hostname),
+            ContainerField::Vmid => Some(&mut self.container_vmid),
+            ContainerField::SshKey => Some(&mut self.container_ssh_key),
+            ContainerField::Cores => Some(&mut self.container_cores),
+            ContainerField::Memory => Some(&mut self.container_memory),
+            ContainerField::DiskSize => Some(&mut self.container_disk),
+            ContainerField::Bridge => Some(&mut self.container_bridge),
+            ContainerField::Address => Some(&mut self.container_address),
+            ContainerField::Gateway => Some(&mut self.container_gateway),
+            ContainerField::Cluster
+            | ContainerField::Node
+            | ContainerField::Template
+            | ContainerField::Storage
+            | ContainerField::StaticIp
+            | ContainerField::AddOverride
+            | ContainerField::Start => None,
+        }
+    }
+
+    /// Space/Left/Right on a choice field: cycle through the loaded options or flip a toggle
+    fn cycle_container_choice(&mut self, forward: bool) {
+        let step = |index: usize, len: usize| {
+            if len == 0 {
+                0
+            } else if forward {
+                (index + 1) % len
+            } else {
+                (index + len - 1) % len
+            }
+        };
+        match self.container_field {
+            ContainerField::Cluster => {
+                let current = self
+                    .container_cluster
+                    .as_ref()
+                    .and_then(|c| self.container_clusters.iter().position(|n| n == c))
+                    .unwrap_or(0);
+                if let Some(cluster) = self.container_clusters.get(step(current, self.container_clusters.len())).cloned() {
+                    self.select_container_cluster(cluster);
+                }
+            }
+            ContainerField::Node if self.container_nodes.len() > 1 => {
+                self.container_node_index = step(self.container_node_index, self.container_nodes.len());
+                self.load_container_options();
+            }
+            ContainerField::Template => {
+                self.container_template_index = step(self.container_template_index, self.container_options.templates.len());
+            }
+            ContainerField::Storage => {
+                self.container_storage_index = step(self.container_storage_index, self.container_options.storages.len());
+            }
+            ContainerField::StaticIp => self.container_static = !self.container_static,
+            ContainerField::AddOverride => self.container_add_override = !self.container_add_override,
+            ContainerField::Start => self.container_start = !self.container_start,
+            _ => {}
+        }
+    }
+
+    /// The container described by the wizard, or the step with the first problem
+    pub fn container_spec(&self) -> Result<ContainerSpec, (ContainerStep, String)> {
+        let placement = |e: &str| (ContainerStep::Placement, e.to_string());
+        self.container_node().ok_or_else(|| placement("Pick a node"))?;
+        let ostemplate = self
+            .container_options
+            .templates
+            .get(self.container_template_index)
+            .cloned()
+            .ok_or_else(|| placement("Pick an OS template"))?;
+
+        let hostname = self.container_hostname.value().trim().to_string();
+        // Hostnames are DNS names
+        let valid_hostname = !hostname.is_empty()
+            && !hostname.starts_with(['-', '.'])
+            && hostname.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.');
+        if !valid_hostname {
+            return Err((ContainerStep::System, "Hostname may only use letters, digits, - and .".to_string()));
+        }
+        let vmid = self
+            .parse_new_vmid(&self.container_vmid, self.container_cluster.as_ref())
+            .map_err(|e| (ContainerStep::System, e))?;
+        let ssh_key = Some(self.container_ssh_key.value().trim().to_string()).filter(|k| !k.is_empty());
+
+        let number = |input: &Input, what: &str| {
+            input
+                .value()
+                .trim()
+                .parse::<u32>()
+                .ok()
+                .filter(|n| *n > 0)
+                .ok_or_else(|| (ContainerStep::Resources, format!("{} must be a positive number", what)))
+        };
+        let cores = number(&self.container_cores, "Cores")?;
+        let memory_mb = number(&self.container_memory, "Memory")?;
+        let rootfs_gb = number(&self.container_disk, "Disk size")?;
+        let rootfs_storage = self
+            .container_options
+            .storages
+            .get(self.container_storage_index)
+            .map(|s| s.storage.clone())
+            .ok_or_else(|| (ContainerStep::Resources, "Pick a storage for the root disk".to_string()))?;
+
+        let bridge = self.container_bridge.value().trim().to_string();
+        if bridge.is_empty() {
+            return Err((ContainerStep::Network, "Enter the bridge to attach to, e.g. vmbr0".to_string()));
+        }
+        let network = if self.container_static {
+            let cidr = self.container_address.value().trim().to_string();
+            if !containers::valid_cidr(&cidr) {
+                return Err((ContainerStep::Network, "Enter the address with its prefix, e.g. 10.0.0.5/24".to_string()));
+            }
+            let gateway = Some(self.container_gateway.value().trim().to_string()).filter(|g| !g.is_empty());
+            if gateway.as_ref().is_some_and(|g| g.parse::<std::net::Ipv4Addr>().is_err()) {
+                return Err((ContainerStep::Network, "The gateway must be an IPv4 address".to_string()));
+            }
+            Network::Static { cidr, gateway }
+        } else {
+            Network::Dhcp
+        };
+
+        Ok(ContainerSpec {
+            vmid,
+            hostname,
+            ostemplate,
+            cores,
+            memory_mb,
+            rootfs_storage,
+            rootfs_gb,
+            bridge,
+            network,
+            ssh_key,
+            start: self.container_start,
+        })
+    }
+
+    /// Enter in the wizard: go to the next step once this one is valid, or create
+    /// the container from the review step
+    fn next_container_step(&mut self) {
+        match self.container_spec() {
+            Err((step, message)) if step <= self.container_step => {
+                self.container_step = step;
+                self.container_field = self.container_fields()[0];
+                self.container_message = Some(message);
+            }
+            _ if self.container_step == ContainerStep::Review => self.submit_create_container(),
+            _ => {
+                self.container_step = self.container_step.next();
+                self.container_field = self.container_fields()[0];
+                self.container_message = None;
+            }
+        }
+    }
+
+    /// Esc in the wizard: back one step, or close it from the first one
+    fn previous_container_step(&mut self) {
+        match self.container_step.previous() {
+            Some(step) => {
+                self.container_step = step;
+                self.container_field = self.container_fields()[0];
+                self.container_message = None;
+            }
+            None => self.close_container_wizard(),
+        }
+    }
+
+    fn close_container_wizard(&mut self) {
+        self.container_cluster = None;
+        self.view_mode = ViewMode::Main;
+    }
+
+    fn submit_create_container(&mut self) {
+        let Ok(spec) = self.container_spec() else {
+            return;
+        };
+        let (Some(cluster), Some(node)) = (self.container_cluster.clone(), self.container_node().map(str::to_string)) else {
+            return;
+        };
+        if proxmox::is_read_only() {
+            self.container_message = Some("Read-only mode: creating containers is disabled".to_string());
+            return;
+        }
+        if !self.cluster_allows(&cluster, spec.vmid, None, permissions::VM_ALLOCATE) {
+            self.container_message = Some(format!("No permission to create guests in {} (needs VM.Allocate)", cluster));
+            return;
+        }
+        let client = match self.client_for(&cluster) {
+            Ok(client) => client,
+            Err(e) => {
+                self.container_message = Some(format!("{:#}", e));
+                return;
+            }
+        };
+
+        // Only static addresses are worth remembering; DHCP ones are read from Proxmox
+        let ip_override = spec.static_ip().filter(|_| self.container_add_override).map(str::to_string);
+        self.close_container_wizard();
+        self.last_error = Some(format!("Creating container {} ({})...", spec.hostname, spec.vmid));
+
+        let tx = self.event_tx.clone();
+        let tasks = TaskHandle::new(self.event_tx.clone());
+        tokio::spawn(async move {
+            let result = async {
+                let upid = client.create_container(&node, &spec).await?;
+                let description = format!("Create container {}", spec.hostname);
+                tasks.follow(&client, &cluster, &upid, description).await
+            }
+            .await;
+
+            let _ = tx.send(AppEvent::ContainerCreated(ContainerOutcome {
+                cluster,
+                vmid: spec.vmid,
+                hostname: spec.hostname,
+                ip_override,
+                result,
+            }));
+        });
+    }
+
+    fn apply_container_created(&mut self, outcome: ContainerOutcome) {
+        if let Err(e) = outcome.result {
+            self.last_error = Some(format!("Creating container {} failed: {:#}", outcome.hostname, e));
+            return;
+        }
+        self.last_error = Some(format!("Container {} ({}) created", outcome.hostname, outcome.vmid));
+
+        if let Some(ip) = outcome.ip_override {
+            let id = HostId::Guest { cluster: outcome.cluster, vmid: outcome.vmid };
+            self.config.ip_overrides.push(IpOverride::for_host(&id, &outcome.hostname, ip));
+            if let Err(e) = self.save_config() {
+                self.last_error = Some(format!("Container {} created, but saving its IP failed: {}", outcome.hostname, e));
+            }
+        }
+        // The new container shows up (with its override applied) after a refresh
+        self.start_refresh();
+    }
+
+    /// Toggle the mark on the selected host and move on to the next one
+    pub fn toggle_mark(&mut self) {
+        let Some(id) = self.selected_host().map(|h| h.id()) else {
+            return;
+        };
+        if !self.marked.remove(&id) {
+            self.marked.insert(id);
+        }
+        self.next();
+    }
+
+    /// Marked hosts, or the selected one if nothing is marked
+    fn action_hosts(&self) -> Vec<Host> {
+        if self.marked.is_empty() {
+            return self.selected_host().cloned().into_iter().collect();
+        }
+        self.hosts.iter().filter(|h| self.marked.contains(&h.id())).cloned().collect()
+    }
+
+    /// Open the backup form for the marked hosts (or the selected one)
+    pub fn start_backup(&mut self) {
+        let hosts = self.action_hosts();
+        self.open_backup_form(hosts);
+    }
+
+    fn open_backup_form(&mut self, hosts: Vec<Host>) {
+        let Some(first) = hosts.first() else {
+            return;
+        };
+        let (Some(cluster), Some(node)) = (first.cluster.clone(), first.node.clone()) else {
+            self.last_error = Some(format!("{} is not a Proxmox guest", first.name));
+            return;
+        };
+        let client = match self.client_for(&cluster) {
+            Ok(client) => client,
+            Err(e) => {
+                self.last_error = Some(format!("{:#}", e));
+                return;
+            }
+        };
+
+        self.backup_targets = hosts.iter().map(|h| h.id()).collect();
+        self.backup_node = Some(node.clone());
+        self.backup_storages.clear();
+        self.backup_storage_index = 0;
+        self.backup_field = BackupField::Storage;
+        self.backup_return = self.view_mode.clone();
+        self.view_mode = ViewMode::BackupCreate;
+        // The form shows problems with the storage list in the status line
+        self.last_error = None;
+
+        // Storages are listed on the first guest's node; shared ones are the same everywhere
+        let tx = self.event_tx.clone();
+        tokio::spawn(async move {
+            let result = client.backup_storages(&node).await;
+            let _ = tx.send(AppEvent::BackupStoragesLoaded { node, result });
+        });
+    }
+
+    fn apply_backup_storages(&mut self, node: String, result: Result<Vec<StorageInfo>>) {
+        if self.view_mode != ViewMode::BackupCreate || self.backup_node.as_ref() != Some(&node) {
+            return;
+        }
+        match result {
+            Ok(storages) if storages.is_empty() => {
+                self.last_error = Some(format!("No backup storage available on {}", node));
+            }
+            Ok(storages) => self.backup_storages = storages,
+            Err(e) => self.last_error = Some(format!("Failed to list storages on {}: {:#}", node, e)),
+        }
+    }
+
+    fn cycle_backup_choice(&mut self) {
+        match self.backup_field {
+            BackupField::Storage if !self.backup_storages.is_empty() => {
+                self.backup_storage_index = (self.backup_storage_index + 1) % self.backup_storages.len();
+            }
+            BackupField::Storage => {}
+            BackupField::Mode => self.backup_mode = self.backup_mode.next(),
+            BackupField::Compression => self.backup_compress = self.backup_compress.next(),
+        }
+    }
+
+    fn submit_backup(&mut self) {
+        let Some(storage) = self.backup_storages.get(self.backup_storage_index) else {
+            self.last_error = Some("Pick a storage to back up to".to_string());
+            return;
+        };
+        let action = GuestAction::Backup {
+            storage: storage.storage.clone(),
+            mode: self.backup_mode,
+            compress: self.backup_compress,
+        };
+        let hosts: Vec<Host> = self.backup_targets.iter().filter_map(|id| self.host_by_id(id).cloned()).collect();
+        let return_to = self.backup_return.clone();
+        self.view_mode = return_to.clone();
+        self.request_action_for(&hosts, action, return_to);
+    }
+
+    /// Open the backup browser for the selected guest
+    pub fn open_backups(&mut self) {
+        let Some(host) = self.selected_host() else {
+            return;
+        };
+        if host.vmid.is_none() || host.node.is_none() || host.cluster.is_none() {
+            self.last_error = Some(format!("{} is not a Proxmox guest", host.name));
+            return;
+        }
+
+        self.backup_host = Some(host.id());
+        self.backups.clear();
+        self.backup_selected = 0;
+        self.view_mode = ViewMode::Backups;
+        self.load_backups();
+    }
+
+    fn load_backups(&mut self) {
+        let Some(host) = self.backup_target().cloned() else {
+            return;
+        };
+        let (Some(cluster), Some(node), Some(vmid)) = (host.cluster.clone(), host.node.clone(), host.vmid) else {
+            return;
+        };
+        let client = match self.client_for(&cluster) {
+            Ok(client) => client,
+            Err(e) => {
+                self.backup_error = Some(format!("{:#}", e));
+                return;
+            }
+        };
+
+        self.backups_loading = true;
+        self.backup_error = None;
+        let tx = self.event_tx.clone();
+        tokio::spawn(async move {
+            let result = client.backups(&node, vmid).await;
+            let _ = tx.send(AppEvent::BackupsLoaded { host: host.id(), result });
+        });
+    }
+
+    fn apply_backups(&mut self, host: HostId, result: Result<Vec<Backup>>) {
+        if self.backup_host.as_ref() != Some(&host) {
+            return;
+        }
+        self.backups_loading = false;
+
+        match result {
+            Ok(backups) => {
+                self.backups = backups;
+                self.backup_selected = self.backup_selected.min(self.backups.len().saturating_sub(1));
+            }
+            Err(e) => self.backup_error = Some(format!("{:#}", e)),
+        }
+    }
+
+    /// Host the backup browser is open for
+    pub fn backup_target(&self) -> Option<&Host> {
+        self.backup_host.as_ref().and_then(|id| self.host_by_id(id))
+    }
+
+    /// Ask for the vmid to restore the selected backup as, suggesting the next free one
+    pub fn start_restore(&mut self) {
+        let Some(backup) = self.backups.get(self.backup_selected) else {
+            return;
+        };
+        let Some(cluster) = self.backup_target().and_then(|h| h.cluster.clone()) else {
+            return;
+        };
+        let Ok(client) = self.client_for(&cluster) else {
+            return;
+        };
+
+        self.restore_archive = Some(backup.volid.clone());
+        self.restore_vmid = Input::default();
+        self.last_error = None;
+        self.view_mode = ViewMode::BackupRestore;
+
+        let tx = self.event_tx.clone();
+        tokio::spawn(async move {
+            let result = client.next_vmid().await;
+            let _ = tx.send(AppEvent::NextVmidLoaded { form: Form::Restore, cluster, result });
+        });
+    }
+
+    fn apply_next_vmid(&mut self, form: Form, cluster: String, result: Result<u32>) {
+        // Only while the form that asked is still open on the same cluster
+        let in_cluster = |host: Option<&Host>| host.and_then(|h| h.cluster.as_ref()) == Some(&cluster);
+        let input = match form {
+            Form::Restore if self.restore_archive.is_some() && in_cluster(self.backup_target()) => &mut self.restore_vmid,
+            Form::Clone if in_cluster(self.clone_target()) => &mut self.clone_vmid,
+            Form::NewContainer if self.container_cluster.as_ref() == Some(&cluster) => &mut self.container_vmid,
+            _ => return,
+        };
+        // Don't overwrite what the user started typing
+        if !input.value().is_empty() {
+            return;
+        }
+        match result {
+            Ok(vmid) => *input = Input::default().with_value(vmid.to_string()),
+            Err(e) => self.last_error = Some(format!("Failed to get a free vmid: {:#}", e)),
+        }
+    }
+
+    /// Parse a vmid typed into a form, checking it is free in `cluster`
+    fn parse_new_vmid(&self, input: &Input, cluster: Option<&String>) -> Result<u32, String> {
+        // Proxmox reserves vmids below 100
+        let Some(vmid) = input.value().trim().parse::<u32>().ok().filter(|id| *id >= 100) else {
+            return Err("The new vmid must be a number of at least 100".to_string());
+        };
+        if self.hosts.iter().any(|h| h.cluster.as_ref() == cluster && h.vmid == Some(vmid)) {
+            return Err(format!("vmid {} is already in use", vmid));
+        }
+        Ok(vmid)
+    }
+
+    fn submit_restore(&mut self) {
+        let Some(host) = self.backup_target().cloned() else {
+            return;
+        };
+        let vmid = match self.parse_new_vmid(&self.restore_vmid, host.cluster.as_ref()) {
+            Ok(vmid) => vmid,
+            Err(e) => {
+                self.last_error = Some(e);
+                return;
+            }
+        };
+        let Some(archive) = self.restore_archive.take() else {
+            return;
+        };
+
+        self.view_mode = ViewMode::Backups;
+        self.request_action(&host, GuestAction::Restore { archive, vmid }, ViewMode::Backups);
+    }
+
+    /// Open the snapshot panel for the selected guest
+    pub fn open_snapshots(&mut self) {
+        let Some(host) = self.selected_host() else {
+            return;
+        };
+        if host.vmid.is_none() || host.node.is_none() || host.cluster.is_none() {
+            self.last_error = Some(format!("{} is not a Proxmox guest", host.name));
+            return;
+        }
+
+        self.snapshot_host = Some(host.id());
+        self.snapshots.clear();
+        self.snapshot_selected = 0;
+        self.view_mode = ViewMode::Snapshots;
+        self.load_snapshots();
+    }
+
+    fn load_snapshots(&mut self) {
+        let Some(host) = self.snapshot_host.as_ref().and_then(|id| self.host_by_id(id)).cloned() else {
+            return;
+        };
+        let (Some(cluster), Some(node), Some(vmid)) = (host.cluster.clone(), host.node.clone(), host.vmid) else {
+            return;
+        };
+        let client = match self.client_for(&cluster) {
+            Ok(client) => client,
+            Err(e) => {
+                self.snapshot_error = Some(format!("{:#}", e));
+                return;
+            }
+        };
+
+        self.snapshots_loading = true;
+        self.snapshot_error = None;
+        let tx = self.event_tx.clone();
+        tokio::spawn(async move {
+            let result = client.snapshots(&node, &host.host_type, vmid).await;
+            let _ = tx.send(AppEvent::SnapshotsLoaded { host: host.id(), result });
+        });
+    }
+
+    fn apply_snapshots(&mut self, host: HostId, result: Result<Vec<Snapshot>>) {
+        if self.snapshot_host.as_ref() != Some(&host) {
+            return;
+        }
+        self.snapshots_loading = false;
+
+        match result {
+            Ok(list) => {
+                let count = snapshots::count(&list);
+                self.snapshot_counts.insert(host.clone(), count);
+                if let Some(host) = self.hosts.iter_mut().find(|h| h.id() == host) {
+                    host.snapshots = Some(count);
+                }
+                self.snapshots = snapshots::tree(&list)
+                    .into_iter()
+                    .map(|(depth, snapshot)| (depth, snapshot.clone()))
+                    .collect();
+                self.snapshot_selected = self.snapshot_selected.min(self.snapshots.len().saturating_sub(1));
+            }
+            Err(e) => self.snapshot_error = Some(format!("{:#}", e)),
+        }
+    }
+
+    /// Host the snapshot panel is open for
+    pub fn snapshot_target(&self) -> Option<&Host> {
+        self.snapshot_host.as_ref().and_then(|id| self.host_by_id(id))
+    }
+
+    /// Snapshot under the cursor, unless it is the "current" pseudo entry
+    fn selected_snapshot(&self) -> Option<&Snapshot> {
+        self.snapshots
+            .get(self.snapshot_selected)
+            .map(|(_, snapshot)| snapshot)
+            .filter(|snapshot| !snapshot.is_current())
+    }
+
+    pub fn start_create_snapshot(&mut self) {
+        if self.snapshot_target().is_none() {
+            return;
+        }
+        self.snapshot_name = Input::default();
+        self.snapshot_description = Input::default();
+        self.snapshot_vmstate = false;
+        self.snapshot_field = SnapshotField::Name;
+        self.view_mode = ViewMode::SnapshotCreate;
+    }
+
+    fn submit_create_snapshot(&mut self) {
+        let name = self.snapshot_name.value().trim().to_string();
+        // Proxmox wants an identifier: a letter followed by letters, digits, - and _
+        let valid = name.chars().next().is_some_and(|c| c.is_ascii_alphabetic())
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        if !valid {
+            self.last_error = Some("Snapshot name must start with a letter and use only letters, digits, - and _".to_string());
+            return;
+        }
+        let Some(host) = self.snapshot_target().cloned() else {
+            return;
+        };
+
+        let action = GuestAction::CreateSnapshot {
+            name,
+            description: self.snapshot_description.value().to_string(),
+            vmstate: self.snapshot_vmstate && host.host_type == HostType::VM,
+        };
+        self.view_mode = ViewMode::Snapshots;
+        self.request_action(&host, action, ViewMode::Snapshots);
+    }
+
+    fn request_snapshot_action(&mut self, action: fn(String) -> GuestAction) {
+        let (Some(host), Some(snapshot)) = (self.snapshot_target().cloned(), self.selected_snapshot()) else {
+            return;
+        };
+        let action = action(snapshot.name.clone());
+        self.request_action(&host, action, ViewMode::Snapshots);
+    }
+
+    pub fn open_task_log(&mut self) {
+        if self.task_selected < self.tasks.tasks.len() {
+            self.task_log_scroll = 0;
+            self.view_mode = ViewMode::TaskLog;
+        }
+    }
+
+    pub fn copy_to_clipboard(&mut self) {
+        if let Ok(mut clipboard) = Clipboard::new() {
+            if let Err(e) = clipboard.set_text(&self.export_content) {
+                self.last_error = Some(format!("Failed to copy to clipboard: {}", e));
+            } else {
+                self.last_error = Some("Copied to clipboard!".to_string());
+            }
+        } else {
+            self.last_error = Some("Failed to access clipboard".to_string());
+        }
+    }
+
+    pub fn start_edit_ip(&mut self) {
+        let Some(host) = self.selected_host().cloned() else {
+            return;
+        };
+
+        self.editing_host_name = host.name.clone();
+        self.editing_host = Some(host.id());
+
+        // Pre-fill with existing IP if available
+        let current_ip = host.ip.clone().unwrap_or_default();
+        self.ip_input = Input::default().with_value(current_ip);
+
+        self.view_mode = ViewMode::EditIp;
+    }
+
+    pub fn save_ip_override(&mut self) -> Result<()> {
+        let new_ip = self.ip_input.value().trim().to_string();
+        let Some(id) = self.editing_host.clone() else {
+            return Ok(());
+        };
+        let name = self.editing_host_name.clone();
+
+        if new_ip.is_empty() {
+            // Remove the override if IP is empty, including a legacy name-only entry
+            self.config
+                .ip_overrides
+                .retain(|o| !o.matches_id(&id) && !o.matches_name(&name));
+
+            // Also remove from the in-memory host list
+            if let Some(host) = self.hosts.iter_mut().find(|h| h.id() == id) {
+                host.ip = None;
+            }
+        } else {
+            // Add or update the override. A legacy name-only entry is upgraded to
+            // an entry pinned to this host.
+            if let Some(existing) = self.config.ip_overrides.iter_mut().find(|o| o.matches_id(&id)) {
+                existing.ip = new_ip.clone();
+            } else if let Some(existing) = self.config.ip_overrides.iter_mut().find(|o| o.matches_name(&name)) {
+                *existing = IpOverride::for_host(&id, &name, new_ip.clone());
+            } else {
+                self.config.ip_overrides.push(IpOverride::for_host(&id, &name, new_ip.clone()));
+            }
+
+            // Immediately update the in-memory host list (instant UI update!)
+            if let Some(host) = self.hosts.iter_mut().find(|h| h.id() == id) {
+                host.ip = Some(new_ip);
+            }
+        }
+
+        // Save to file
+        self.save_config()?;
+
+        self.last_error = Some(format!("IP saved for {}", self.editing_host_name));
+        self.view_mode = ViewMode::Main;
+        // No need for needs_refresh - we updated in-memory immediately!
+
+        Ok(())
+    }
+
+    /// Save the Proxmox cluster each entry belongs to, so the entries stay grouped
+    /// under the same name on the next start even if some are down then
+    fn remember_membership(&mut self, clusters: &[LogicalCluster]) {
+        let mut changed = false;
+        for cluster in clusters {
+            let Some(pve_cluster) = &cluster.pve_cluster else {
+                continue;
+            };
+            for entry in self.config.proxmox_hosts.iter_mut().filter(|h| cluster.members.contains(&h.name)) {
+                if entry.pve_cluster.as_ref() != Some(pve_cluster) {
+                    entry.pve_cluster = Some(pve_cluster.clone());
+                    changed = true;
+                }
+            }
+        }
+        if changed {
+            if let Err(e) = self.save_config() {
+                self.last_error = Some(format!("Failed to save cluster membership: {}", e));
+            }
+        }
+    }
+
+    fn save_config(&self) -> Result<()> {
+        // Ensure parent directory exists
+        if let Some(parent) = std::path::Path::new(&self.config_path).parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        self.config.save(&self.config_path)
+    }
+
+    pub fn start_setup(&mut self) {
+        // Reset form fields
+        self.setup_name = Input::default();
+        self.setup_host = Input::default();
+        self.setup_port = Input::default().with_value("8006".to_string());
+        self.setup_endpoints = Input::default();
+        self.setup_proxy = Input::default();
+        self.setup_token_id = Input::default();
+        self.setup_token_secret = Input::default();
+        self.setup_verify_ssl = false;
+        self.setup_method = SetupMethod::ExistingToken;
+        self.setup_username = Input::default();
+        self.setup_realm = Input::default().with_value("pam".to_string());
+        self.setup_password = Input::default();
+        self.setup_totp = Input::default();
+        self.setup_role = TokenRole::ReadOnly;
+        self.setup_message = None;
+        self.setup_busy = false;
+        self.setup_pending_host = None;
+        self.setup_failed_field = None;
+        self.setup_offered_fingerprint = None;
+        self.setup_fingerprint = None;
+        self.setup_field = SetupField::Name;
+        self.view_mode = ViewMode::Setup;
+    }
+
+    /// Fields of the Setup form in display order; depends on the chosen method
+    pub fn setup_fields(&self) -> Vec<SetupField> {
+        let mut fields = vec![
+            SetupField::Method,
+            SetupField::Name,
+            SetupField::Host,
+            SetupField::Port,
+            SetupField::Endpoints,
+            SetupField::Proxy,
+        ];
+        match self.setup_method {
+            SetupMethod::ExistingToken => {
+                fields.extend([SetupField::ApiTokenId, SetupField::ApiTokenSecret]);
+            }
+            SetupMethod::CreateToken => {
+                fields.extend([
+                    SetupField::Username,
+                    SetupField::Realm,
+                    SetupField::Password,
+                    SetupField::Totp,
+                    SetupField::Role,
+                ]);
+            }
+        }
+        fields.push(SetupField::VerifySsl);
+        fields
+    }
+
+    pub fn next_setup_field(&mut self) {
+        let fields = self.setup_fields();
+        let index = fields.iter().position(|f| *f == self.setup_field).unwrap_or(0);
+        self.setup_field = fields[(index + 1) % fields.len()];
+    }
+
+    pub fn prev_setup_field(&mut self) {
+        let fields = self.setup_fields();
+        let index = fields.iter().position(|f| *f == self.setup_field).unwrap_or(0);
+        self.setup_field = fields[(index + fields.len() - 1) % fields.len()];
+    }
+
+    /// Space on a choice field: switch method, cycle the role or toggle SSL verification
+    fn toggle_setup_choice(&mut self) {
+        match self.setup_field {
+            SetupField::Method => {
+                self.setup_method = match self.setup_method {
+                    SetupMethod::ExistingToken => SetupMethod::CreateToken,
+                    SetupMethod::CreateToken => SetupMethod::ExistingToken,
+                };
+            }
+            SetupField::Role => self.setup_role = self.setup_role.next(),
+            SetupField::VerifySsl => self.setup_verify_ssl = !self.setup_verify_ssl,
+            _ => {}
+        }
+    }
+
+    /// Whether the focused Setup field is a choice rather than a text input
+    pub fn setup_field_is_choice(&self) -> bool {
+        matches!(self.setup_field, SetupField::Method | SetupField::Role | SetupField::VerifySsl)
+    }
+
+    /// Text input behind the focused Setup field, None for choice fields
+    fn setup_input_mut(&mut self) -> Option<&mut Input> {
+        match self.setup_field {
+            SetupField::Name => Some(&mut self.setup_name),
+            SetupField::Host => Some(&mut self.setup_host),
+            SetupField::Port => Some(&mut self.setup_port),
+            SetupField::Endpoints => Some(&mut self.setup_endpoints),
+            SetupField::Proxy => Some(&mut self.setup_proxy),
+            SetupField::ApiTokenId => Some(&mut self.setup_token_id),
+            SetupField::ApiTokenSecret => Some(&mut self.setup_token_secret),
+            SetupField::Username => Some(&mut self.setup_username),
+            SetupField::Realm => Some(&mut self.setup_realm),
+            SetupField::Password => Some(&mut self.setup_password),
+            SetupField::Totp => Some(&mut self.setup_totp),
+            SetupField::Method | SetupField::Role | SetupField::VerifySsl => None,
+        }
+    }
+
+    fn edit_setup_input(&mut self, request: tui_input::InputRequest) {
+        // An accepted certificate belongs to the address it was fetched from
+        if matches!(self.setup_field, SetupField::Host | SetupField::Port | SetupField::Proxy) {
+            self.setup_fingerprint = None;
+        }
+        if let Some(input) = self.setup_input_mut() {
+            input.handle(request);
+        }
+    }
+
+    pub fn save_proxmox_host(&mut self) -> Result<()> {
+        if self.setup_busy {
+            return Ok(());
+        }
+
+        // Validate fields
+        let name = self.setup_name.value().trim();
+        let host = self.setup_host.value().trim();
+        let port_str = self.setup_port.value().trim();
+        let token_id = self.setup_token_id.value().trim();
+        let token_secret = self.setup_token_secret.value().trim();
+        let username = self.setup_username.value().trim();
+
+        let credentials_missing = match self.setup_method {
+            SetupMethod::ExistingToken => token_id.is_empty() || token_secret.is_empty(),
+            SetupMethod::CreateToken => username.is_empty() || self.setup_password.value().is_empty(),
+        };
+        if name.is_empty() || host.is_empty() || credentials_missing {
+            self.setup_message = Some("All fields except port, endpoints, proxy, realm and TOTP are required".to_string());
+            return Ok(());
+        }
+
+        let port: u16 = port_str.parse().unwrap_or(8006);
+        let endpoints = self
+            .setup_endpoints
+            .value()
+            .split([',', ' '])
+            .filter(|e| !e.is_empty())
+            .map(|e| e.to_string())
+            .collect();
+        let proxy = Some(self.setup_proxy.value().trim().to_string()).filter(|p| !p.is_empty());
+
+        // Check for duplicate name
+        if self.config.proxmox_hosts.iter().any(|h| h.name == name) {
+            self.setup_message = Some(format!("Host '{}' already exists", name));
+            return Ok(());
+        }
+
+        // Without CA verification, trust the server's certificate only once the user
+        // has seen and accepted its fingerprint, before any credentials are sent
+        if !self.setup_verify_ssl && self.setup_fingerprint.is_none() {
+            self.fetch_setup_fingerprint(host.to_string(), port, proxy);
+            return Ok(());
+        }
+
+        let new_host = ProxmoxHost {
+            name: name.to_string(),
+            host: host.to_string(),
+            port,
+            endpoints,
+            proxy,
+            verify_ssl: self.setup_verify_ssl,
+            fingerprint: if self.setup_verify_ssl { None } else { self.setup_fingerprint.clone() },
+            ..Default::default()
+        };
+
+        match self.setup_method {
+            SetupMethod::ExistingToken => {
+                let mut new_host = ProxmoxHost {
+                    api_token_id: token_id.to_string(),
+                    api_token_secret: token_secret.to_string(),
+                    ..new_host
+                };
+                // Secret references (env:, file:, cmd:) work here as well. A cmd: may
+                // take a while, so look it up off the UI thread.
+                self.setup_busy = true;
+                self.setup_message = Some("Resolving API token secret...".to_string());
+                let tx = self.event_tx.clone();
+                tokio::task::spawn_blocking(move || {
+                    new_host.resolve_secret();
+                    let _ = tx.send(AppEvent::SetupSecretResolved(new_host));
+                });
+            }
+            SetupMethod::CreateToken => {
+                let realm = self.setup_realm.value().trim();
+                let new_host = ProxmoxHost {
+                    username: username.to_string(),
+                    realm: (!realm.is_empty()).then(|| realm.to_string()),
+                    ..new_host
+                };
+
+                // The password leaves the form right away and is never written anywhere
+                let password = std::mem::take(&mut self.setup_password).value().to_string();
+                let totp = std::mem::take(&mut self.setup_totp).value().to_string();
+                let role = self.setup_role;
+
+                self.setup_busy = true;
+                self.setup_message = Some("Logging in and creating API token...".to_string());
+                let tx = self.event_tx.clone();
+                tokio::spawn(async move {
+                    let result = bootstrap_token(new_host, password, totp, role).await;
+                    let _ = tx.send(AppEvent::TokenCreated(result));
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    fn fetch_setup_fingerprint(&mut self, host: String, port: u16, proxy: Option<String>) {
+        self.setup_busy = true;
+        self.setup_message = Some(format!("Fetching certificate from {}...", host));
+        let tx = self.event_tx.clone();
+        tokio::spawn(async move {
+            let result = tls::fetch_fingerprint(&host, port, proxy.as_deref()).await;
+            let _ = tx.send(AppEvent::SetupFingerprint(result));
+        });
+    }
+
+    fn apply_setup_fingerprint(&mut self, result: Result<String>) {
+        if self.view_mode != ViewMode::Setup {
+            return;
+        }
+        self.setup_busy = false;
+
+        match result {
+            Ok(fingerprint) => {
+                self.setup_message = Some(format!(
+                    "Trust this certificate? SHA-256 {} (compare with Node → System → Certificates in the web UI)",
+                    fingerprint
+                ));
+                self.setup_offered_fingerprint = Some(fingerprint);
+            }
+            Err(e) => {
+                self.setup_message = Some(format!("Could not fetch the certificate: {:#}", e));
+                self.setup_field = SetupField::Host;
+            }
+        }
+    }
+
+    /// Pin the offered certificate and carry on saving
+    fn accept_setup_fingerprint(&mut self) {
+        self.setup_fingerprint = self.setup_offered_fingerprint.take();
+        self.setup_message = None;
+        if let Err(e) = self.save_proxmox_host() {
+            self.setup_message = Some(format!("Failed to save: {}", e));
+        }
+    }
+
+    fn reject_setup_fingerprint(&mut self) {
+        self.setup_offered_fingerprint = None;
+        self.setup_message = Some("Certificate not trusted. Check the host, or enable Verify SSL for a CA-signed certificate".to_string());
+        self.setup_field = SetupField::Host;
+    }
+
+    fn add_proxmox_host(&mut self, new_host: ProxmoxHost) -> Result<()> {
+        let name = new_host.name.clone();
+        self.config.proxmox_hosts.push(new_host);
+        self.save_config()?;
+
+        self.last_error = Some(format!("Added Proxmox host '{}'", name));
+        self.view_mode = ViewMode::Main;
+
+        Ok(())
+    }
+
+    fn apply_token_created(&mut self, result: Result<ProxmoxHost>) {
+        self.setup_busy = false;
+
+        match result {
+            // The form was closed while the token was being created; keep the token anyway
+            Ok(new_host) if self.view_mode != ViewMode::Setup => {
+                let token_id = new_host.api_token_id.clone();
+                let name = new_host.name.clone();
+                match self.add_proxmox_host(new_host) {
+                    Ok(()) => {
+                        self.last_error = Some(format!("Created API token {} and added Proxmox host '{}'", token_id, name));
+                    }
+                    Err(e) => {
+                        self.last_error = Some(format!("Created API token {} but failed to save: {}", token_id, e));
+                    }
+                }
+            }
+            Ok(new_host) => {
+                // Continue as if the token had been pasted, so going back to a field
+                // after a failed check doesn't try to create it a second time
+                self.setup_method = SetupMethod::ExistingToken;
+                self.setup_token_id = Input::default().with_value(new_host.api_token_id.clone());
+                self.setup_token_secret = Input::default().with_value(new_host.api_token_secret.clone());
+                if !self.setup_fields().contains(&self.setup_field) {
+                    self.setup_field = SetupField::VerifySsl;
+                }
+                self.check_proxmox_host(new_host);
+            }
+            Err(e) => {
+                self.setup_message = Some(format!("{:#}", e));
+            }
+        }
+    }
+
+    /// Query /version and /nodes with the new host's settings before saving it
+    fn apply_setup_secret(&mut self, new_host: ProxmoxHost) {
+        // Cancelled while the secret was looked up
+        if self.view_mode != ViewMode::Setup {
+            return;
         }
+        self.setup_busy = false;
+        self.check_proxmox_host(new_host);
+    }
 
-        let port: u16 = port_str.parse().unwrap_or(8006);
+    fn check_proxmox_host(&mut self, new_host: ProxmoxHost) {
+        let client = match ProxmoxClient::new(&new_host, &self.sessions) {
+            Ok(client) => client,
+            Err(e) => {
+                self.setup_message = Some(format!("{:#}", e));
+                return;
+            }
+        };
 
-        // Check for duplicate name
-        if self.config.proxmox_hosts.iter().any(|h| h.name == name) {
-            self.last_error = Some(format!("Host '{}' already exists", name));
-            return Ok(());
+        self.setup_busy = true;
+        self.setup_message = Some(format!("Checking connection to {}...", new_host.host));
+        let tx = self.event_tx.clone();
+        tokio::spawn(async move {
+            let result = client.check_connection().await;
+            let _ = tx.send(AppEvent::SetupChecked { host: new_host, result });
+        });
+    }
+
+    fn apply_setup_check(&mut self, new_host: ProxmoxHost, result: Result<ConnectionReport>) {
+        // Cancelled while the check was running
+        if self.view_mode != ViewMode::Setup {
+            return;
         }
+        self.setup_busy = false;
 
-        // Add new host
-        let new_host = ProxmoxHost {
-            name: name.to_string(),
-            host: host.to_string(),
-            port,
-            api_token_id: token_id.to_string(),
-            api_token_secret: token_secret.to_string(),
-            verify_ssl: self.setup_verify_ssl,
+        let (message, failed_field) = match result {
+            Ok(report) if report.warnings.is_empty() => {
+                let name = new_host.name.clone();
+                match self.add_proxmox_host(new_host) {
+                    Ok(()) => {
+                        self.last_error = Some(format!(
+                            "Added Proxmox host '{}' (PVE {}, {} node{})",
+                            name,
+                            report.version,
+                            report.nodes,
+                            if report.nodes == 1 { "" } else { "s" },
+                        ));
+                    }
+                    Err(e) => self.setup_message = Some(format!("Failed to save: {}", e)),
+                }
+                return;
+            }
+            Ok(report) => (
+                format!(
+                    "Connected to PVE {} ({} nodes), but: {}",
+                    report.version,
+                    report.nodes,
+                    report.warnings.join("; "),
+                ),
+                SetupField::ApiTokenId,
+            ),
+            Err(e) => {
+                let status = ClusterStatus::from_error(&e);
+                let field = match status {
+                    ClusterStatus::AuthFailed => SetupField::ApiTokenId,
+                    ClusterStatus::TlsError | ClusterStatus::CertificateChanged => SetupField::VerifySsl,
+                    _ => SetupField::Host,
+                };
+                (format!("Check failed ({}): {:#}", status.as_str(), e), field)
+            }
         };
 
-        self.config.proxmox_hosts.push(new_host);
-        self.save_config()?;
+        self.setup_message = Some(message);
+        self.setup_pending_host = Some(new_host);
+        self.setup_failed_field = Some(failed_field);
+    }
 
-        self.last_error = Some(format!("Added Proxmox host '{}'", name));
-        self.view_mode = ViewMode::Main;
+    /// Save the host whose connection check failed
+    fn save_unchecked_host(&mut self) {
+        if let Some(new_host) = self.setup_pending_host.take() {
+            self.setup_failed_field = None;
+            if let Err(e) = self.add_proxmox_host(new_host) {
+                self.setup_message = Some(format!("Failed to save: {}", e));
+            }
+        }
+    }
 
-        Ok(())
+    /// Drop the failed check and focus the field that most likely caused it
+    fn back_to_failed_field(&mut self) {
+        self.setup_pending_host = None;
+        if let Some(field) = self.setup_failed_field.take() {
+            self.setup_field = field;
+        }
     }
 
     pub fn set_sort_column(&mut self, column: SortColumn) {
@@ -380,6 +2785,8 @@ impl App {
     }
 
     pub fn apply_sort(&mut self) {
+        let selected = self.selected_host().map(|h| h.id());
+
         let direction_multiplier = match self.sort_direction {
             SortDirection::Ascending => 1,
             SortDirection::Descending => -1,
@@ -388,7 +2795,7 @@ impl App {
         self.hosts.sort_by(|a, b| {
             let cmp = match self.sort_column {
                 SortColumn::Name => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
-                SortColumn::Type => a.host_type.as_str().cmp(&b.host_type.as_str()),
+                SortColumn::Type => a.host_type.as_str().cmp(b.host_type.as_str()),
                 SortColumn::Status => a.status.cmp(&b.status),
                 SortColumn::IpAddress => {
                     // Sort IPs, putting N/A at the end
@@ -423,11 +2830,51 @@ impl App {
             }
         });
 
-        // Reset selection to top after sorting
-        self.selected_index = 0;
+        // Keep the cursor on the same host after sorting
+        self.select_host(selected.as_ref());
+    }
+
+    fn handle_login_key(&mut self, key: KeyEvent) {
+        if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) {
+            self.should_quit = true;
+            return;
+        }
+
+        let input = if self.login_challenge.is_some() {
+            &mut self.login_totp
+        } else {
+            &mut self.login_password
+        };
+        match key.code {
+            KeyCode::Esc => self.skip_login(),
+            KeyCode::Enter => self.submit_login(),
+            KeyCode::Char(c) => {
+                input.handle(tui_input::InputRequest::InsertChar(c));
+            }
+            KeyCode::Backspace => {
+                input.handle(tui_input::InputRequest::DeletePrevChar);
+            }
+            _ => {}
+        }
     }
 
     pub fn handle_key_event(&mut self, key: KeyEvent) {
+        // The login prompt can show up before the first fetch
+        if self.view_mode == ViewMode::Login {
+            self.handle_login_key(key);
+            return;
+        }
+
+        // Only allow quitting while the loading screen is shown
+        if !self.initial_fetch_done {
+            if matches!(key.code, KeyCode::Char('q') | KeyCode::Char('Q'))
+                || (key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL))
+            {
+                self.should_quit = true;
+            }
+            return;
+        }
+
         match self.view_mode {
             ViewMode::Main => {
                 match key.code {
@@ -464,6 +2911,66 @@ impl App {
                     KeyCode::Char('a') | KeyCode::Char('A') => {
                         self.start_setup();
                     }
+                    KeyCode::Char('l') => {
+                        self.relogin();
+                    }
+                    KeyCode::Char('r') | KeyCode::Char('R') => {
+                        self.start_refresh();
+                    }
+                    KeyCode::Char('p') | KeyCode::Char('P') => {
+                        self.toggle_auto_refresh();
+                    }
+                    KeyCode::Char('t') | KeyCode::Char('T') => {
+                        self.view_mode = ViewMode::Tasks;
+                    }
+                    KeyCode::Char('c') | KeyCode::Char('C') => {
+                        self.view_mode = ViewMode::Clusters;
+                    }
+                    KeyCode::Char('s') => {
+                        self.request_power_action(PowerAction::Start);
+                    }
+                    KeyCode::Char('x') => {
+                        self.request_power_action(PowerAction::Shutdown);
+                    }
+                    KeyCode::Char('X') => {
+                        self.request_power_action(PowerAction::Stop);
+                    }
+                    KeyCode::Char('b') => {
+                        self.request_power_action(PowerAction::Reboot);
+                    }
+                    KeyCode::Char('v') => {
+                        self.start_backup();
+                    }
+                    KeyCode::Char('m') | KeyCode::Char('M') => {
+                        self.start_migrate();
+                    }
+                    KeyCode::Char('n') => {
+                        self.start_clone();
+                    }
+                    KeyCode::Char('N') => {
+                        self.start_create_container();
+                    }
+                    KeyCode::Char('f') | KeyCode::Char('F') => {
+                        self.cycle_host_filter();
+                    }
+                    KeyCode::Char('B') => {
+                        self.open_backups();
+                    }
+                    KeyCode::Char(' ') => {
+                        self.toggle_mark();
+                    }
+                    KeyCode::Esc => {
+                        self.marked.clear();
+                    }
+                    KeyCode::Char('z') | KeyCode::Char('Z') => {
+                        self.request_power_action(PowerAction::Suspend);
+                    }
+                    KeyCode::Char('u') | KeyCode::Char('U') => {
+                        self.request_power_action(PowerAction::Resume);
+                    }
+                    KeyCode::Char('S') => {
+                        self.open_snapshots();
+                    }
                     KeyCode::Char('1') => {
                         self.set_sort_column(SortColumn::Name);
                     }
@@ -496,6 +3003,326 @@ impl App {
                     _ => {}
                 }
             }
+            ViewMode::ConfirmAction if self.confirm_name.is_some() => {
+                match key.code {
+                    KeyCode::Enter => {
+                        self.confirm_action();
+                    }
+                    KeyCode::Esc => {
+                        self.cancel_action();
+                    }
+                    KeyCode::Char(c) => {
+                        self.confirm_input.handle(tui_input::InputRequest::InsertChar(c));
+                    }
+                    KeyCode::Backspace => {
+                        self.confirm_input.handle(tui_input::InputRequest::DeletePrevChar);
+                    }
+                    _ => {}
+                }
+            }
+            ViewMode::ConfirmAction => {
+                match key.code {
+                    KeyCode::Char('y') | KeyCode::Char('Y') | KeyCode::Enter => {
+                        self.confirm_action();
+                    }
+                    KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => {
+                        self.cancel_action();
+                    }
+                    _ => {}
+                }
+            }
+            ViewMode::Snapshots => {
+                match key.code {
+                    KeyCode::Esc | KeyCode::Char('q') => {
+                        self.snapshot_host = None;
+                        self.view_mode = ViewMode::Main;
+                    }
+                    KeyCode::Down | KeyCode::Char('j') if self.snapshot_selected + 1 < self.snapshots.len() => {
+                        self.snapshot_selected += 1;
+                    }
+                    KeyCode::Up | KeyCode::Char('k') => {
+                        self.snapshot_selected = self.snapshot_selected.saturating_sub(1);
+                    }
+                    KeyCode::Char('n') => {
+                        self.start_create_snapshot();
+                    }
+                    KeyCode::Char('R') => {
+                        self.request_snapshot_action(GuestAction::RollbackSnapshot);
+                    }
+                    KeyCode::Char('d') | KeyCode::Delete => {
+                        self.request_snapshot_action(GuestAction::DeleteSnapshot);
+                    }
+                    KeyCode::Char('r') => {
+                        self.load_snapshots();
+                    }
+                    _ => {}
+                }
+            }
+            ViewMode::SnapshotCreate => {
+                match key.code {
+                    KeyCode::Esc => {
+                        self.view_mode = ViewMode::Snapshots;
+                    }
+                    KeyCode::Enter => {
+                        self.submit_create_snapshot();
+                    }
+                    KeyCode::Tab | KeyCode::Down => {
+                        self.snapshot_field = match self.snapshot_field {
+                            SnapshotField::Name => SnapshotField::Description,
+                            SnapshotField::Description => SnapshotField::Vmstate,
+                            SnapshotField::Vmstate => SnapshotField::Name,
+                        };
+                    }
+                    KeyCode::BackTab | KeyCode::Up => {
+                        self.snapshot_field = match self.snapshot_field {
+                            SnapshotField::Name => SnapshotField::Vmstate,
+                            SnapshotField::Description => SnapshotField::Name,
+                            SnapshotField::Vmstate => SnapshotField::Description,
+                        };
+                    }
+                    KeyCode::Char(' ') if self.snapshot_field == SnapshotField::Vmstate => {
+                        self.snapshot_vmstate = !self.snapshot_vmstate;
+                    }
+                    KeyCode::Char(c) => {
+                        match self.snapshot_field {
+                            SnapshotField::Name => self.snapshot_name.handle(tui_input::InputRequest::InsertChar(c)),
+                            SnapshotField::Description => self.snapshot_description.handle(tui_input::InputRequest::InsertChar(c)),
+                            SnapshotField::Vmstate => None,
+                        };
+                    }
+                    KeyCode::Backspace => {
+                        match self.snapshot_field {
+                            SnapshotField::Name => self.snapshot_name.handle(tui_input::InputRequest::DeletePrevChar),
+                            SnapshotField::Description => self.snapshot_description.handle(tui_input::InputRequest::DeletePrevChar),
+                            SnapshotField::Vmstate => None,
+                        };
+                    }
+                    _ => {}
+                }
+            }
+            ViewMode::Backups => {
+                match key.code {
+                    KeyCode::Esc | KeyCode::Char('q') => {
+                        self.backup_host = None;
+                        self.view_mode = ViewMode::Main;
+                    }
+                    KeyCode::Down | KeyCode::Char('j') if self.backup_selected + 1 < self.backups.len() => {
+                        self.backup_selected += 1;
+                    }
+                    KeyCode::Up | KeyCode::Char('k') => {
+                        self.backup_selected = self.backup_selected.saturating_sub(1);
+                    }
+                    KeyCode::Char('n') => {
+                        if let Some(host) = self.backup_target().cloned() {
+                            self.open_backup_form(vec![host]);
+                        }
+                    }
+                    KeyCode::Enter | KeyCode::Char('R') => {
+                        self.start_restore();
+                    }
+                    KeyCode::Char('r') => {
+                        self.load_backups();
+                    }
+                    _ => {}
+                }
+            }
+            ViewMode::BackupCreate => {
+                match key.code {
+                    KeyCode::Esc => {
+                        self.view_mode = self.backup_return.clone();
+                    }
+                    KeyCode::Enter => {
+                        self.submit_backup();
+                    }
+                    KeyCode::Tab | KeyCode::Down => {
+                        self.backup_field = match self.backup_field {
+                            BackupField::Storage => BackupField::Mode,
+                            BackupField::Mode => BackupField::Compression,
+                            BackupField::Compression => BackupField::Storage,
+                        };
+                    }
+                    KeyCode::BackTab | KeyCode::Up => {
+                        self.backup_field = match self.backup_field {
+                            BackupField::Storage => BackupField::Compression,
+                            BackupField::Mode => BackupField::Storage,
+                            BackupField::Compression => BackupField::Mode,
+                        };
+                    }
+                    KeyCode::Char(' ') | KeyCode::Right => {
+                        self.cycle_backup_choice();
+                    }
+                    _ => {}
+                }
+            }
+            ViewMode::ContainerCreate => {
+                let on_choice = matches!(
+                    self.container_field,
+                    ContainerField::Cluster
+                        | ContainerField::Node
+                        | ContainerField::Template
+                        | ContainerField::Storage
+                        | ContainerField::StaticIp
+                        | ContainerField::AddOverride
+                        | ContainerField::Start
+                );
+                match key.code {
+                    KeyCode::Esc => {
+                        self.previous_container_step();
+                    }
+                    KeyCode::Enter => {
+                        self.next_container_step();
+                    }
+                    KeyCode::Tab | KeyCode::Down => {
+                        self.move_container_field(true);
+                    }
+                    KeyCode::BackTab | KeyCode::Up => {
+                        self.move_container_field(false);
+                    }
+                    KeyCode::Char(' ') | KeyCode::Right if on_choice => {
+                        self.cycle_container_choice(true);
+                    }
+                    KeyCode::Left if on_choice => {
+                        self.cycle_container_choice(false);
+                    }
+                    KeyCode::Char(c) => {
+                        if let Some(input) = self.container_input_mut() {
+                            input.handle(tui_input::InputRequest::InsertChar(c));
+                        }
+                    }
+                    KeyCode::Backspace => {
+                        if let Some(input) = self.container_input_mut() {
+                            input.handle(tui_input::InputRequest::DeletePrevChar);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            ViewMode::Clone => {
+                match key.code {
+                    KeyCode::Esc => {
+                        self.clone_source = None;
+                        self.view_mode = ViewMode::Main;
+                    }
+                    KeyCode::Enter => {
+                        self.submit_clone();
+                    }
+                    KeyCode::Tab | KeyCode::Down => {
+                        self.move_clone_field(true);
+                    }
+                    KeyCode::BackTab | KeyCode::Up => {
+                        self.move_clone_field(false);
+                    }
+                    KeyCode::Char(' ') if matches!(self.clone_field, CloneField::Node | CloneField::Mode | CloneField::Start) => {
+                        self.toggle_clone_choice();
+                    }
+                    KeyCode::Char(c) => {
+                        if let Some(input) = self.clone_input_mut() {
+                            input.handle(tui_input::InputRequest::InsertChar(c));
+                        }
+                    }
+                    KeyCode::Backspace => {
+                        if let Some(input) = self.clone_input_mut() {
+                            input.handle(tui_input::InputRequest::DeletePrevChar);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            ViewMode::Migrate => {
+                match key.code {
+                    KeyCode::Esc | KeyCode::Char('q') => {
+                        self.migrate_host = None;
+                        self.view_mode = ViewMode::Main;
+                    }
+                    KeyCode::Down | KeyCode::Char('j') if self.migrate_selected + 1 < self.migrate_nodes.len() => {
+                        self.migrate_selected += 1;
+                    }
+                    KeyCode::Up | KeyCode::Char('k') => {
+                        self.migrate_selected = self.migrate_selected.saturating_sub(1);
+                    }
+                    KeyCode::Enter => {
+                        self.submit_migrate();
+                    }
+                    _ => {}
+                }
+            }
+            ViewMode::BackupRestore => {
+                match key.code {
+                    KeyCode::Esc => {
+                        self.restore_archive = None;
+                        self.view_mode = ViewMode::Backups;
+                    }
+                    KeyCode::Enter => {
+                        self.submit_restore();
+                    }
+                    KeyCode::Char(c) if c.is_ascii_digit() => {
+                        self.restore_vmid.handle(tui_input::InputRequest::InsertChar(c));
+                    }
+                    KeyCode::Backspace => {
+                        self.restore_vmid.handle(tui_input::InputRequest::DeletePrevChar);
+                    }
+                    _ => {}
+                }
+            }
+            ViewMode::Clusters => {
+                match key.code {
+                    KeyCode::Esc | KeyCode::Enter | KeyCode::Char('q') | KeyCode::Char('c') => {
+                        self.view_mode = ViewMode::Main;
+                    }
+                    _ => {}
+                }
+            }
+            // Handled before the loading screen check
+            ViewMode::Login => {}
+            ViewMode::Tasks => {
+                match key.code {
+                    KeyCode::Esc | KeyCode::Char('q') => {
+                        self.view_mode = ViewMode::Main;
+                    }
+                    KeyCode::Down | KeyCode::Char('j') if self.task_selected + 1 < self.tasks.tasks.len() => {
+                        self.task_selected += 1;
+                    }
+                    KeyCode::Up | KeyCode::Char('k') => {
+                        self.task_selected = self.task_selected.saturating_sub(1);
+                    }
+                    KeyCode::Enter => {
+                        self.open_task_log();
+                    }
+                    _ => {}
+                }
+            }
+            ViewMode::TaskLog => {
+                let log_len = self
+                    .tasks
+                    .tasks
+                    .get(self.task_selected)
+                    .map(|t| t.log.len())
+                    .unwrap_or(0);
+                match key.code {
+                    KeyCode::Esc | KeyCode::Enter | KeyCode::Char('q') => {
+                        self.view_mode = ViewMode::Tasks;
+                    }
+                    KeyCode::Down | KeyCode::Char('j') => {
+                        self.task_log_scroll = (self.task_log_scroll + 1).min(log_len.saturating_sub(1));
+                    }
+                    KeyCode::Up | KeyCode::Char('k') => {
+                        self.task_log_scroll = self.task_log_scroll.saturating_sub(1);
+                    }
+                    KeyCode::PageDown => {
+                        self.task_log_scroll = (self.task_log_scroll + 10).min(log_len.saturating_sub(1));
+                    }
+                    KeyCode::PageUp => {
+                        self.task_log_scroll = self.task_log_scroll.saturating_sub(10);
+                    }
+                    KeyCode::Home | KeyCode::Char('g') => {
+                        self.task_log_scroll = 0;
+                    }
+                    KeyCode::End | KeyCode::Char('G') => {
+                        self.task_log_scroll = log_len.saturating_sub(1);
+                    }
+                    _ => {}
+                }
+            }
             ViewMode::EditIp => {
                 match key.code {
                     KeyCode::Enter => {
@@ -531,6 +3358,22 @@ impl App {
                     _ => {}
                 }
             }
+            // An offered certificate waits for the user to trust it or not
+            ViewMode::Setup if self.setup_offered_fingerprint.is_some() => {
+                match key.code {
+                    KeyCode::Enter => self.accept_setup_fingerprint(),
+                    KeyCode::Esc => self.reject_setup_fingerprint(),
+                    _ => {}
+                }
+            }
+            // A failed connection check waits for "save anyway" or "go back"
+            ViewMode::Setup if self.setup_pending_host.is_some() => {
+                match key.code {
+                    KeyCode::Enter => self.save_unchecked_host(),
+                    KeyCode::Esc => self.back_to_failed_field(),
+                    _ => {}
+                }
+            }
             ViewMode::Setup => {
                 match key.code {
                     KeyCode::Esc => {
@@ -545,66 +3388,30 @@ impl App {
                     KeyCode::Enter if self.setup_field == SetupField::VerifySsl => {
                         // Submit form
                         if let Err(e) = self.save_proxmox_host() {
-                            self.last_error = Some(format!("Failed to save: {}", e));
+                            self.setup_message = Some(format!("Failed to save: {}", e));
                         }
                     }
-                    KeyCode::Char(' ') if self.setup_field == SetupField::VerifySsl => {
-                        self.setup_verify_ssl = !self.setup_verify_ssl;
+                    KeyCode::Char(' ') if self.setup_field_is_choice() => {
+                        self.toggle_setup_choice();
                     }
                     KeyCode::Char(c) => {
-                        let input = match self.setup_field {
-                            SetupField::Name => &mut self.setup_name,
-                            SetupField::Host => &mut self.setup_host,
-                            SetupField::Port => &mut self.setup_port,
-                            SetupField::ApiTokenId => &mut self.setup_token_id,
-                            SetupField::ApiTokenSecret => &mut self.setup_token_secret,
-                            SetupField::VerifySsl => return, // Skip for checkbox
-                        };
-                        input.handle(tui_input::InputRequest::InsertChar(c));
+                        self.edit_setup_input(tui_input::InputRequest::InsertChar(c));
                     }
                     KeyCode::Backspace => {
-                        let input = match self.setup_field {
-                            SetupField::Name => &mut self.setup_name,
-                            SetupField::Host => &mut self.setup_host,
-                            SetupField::Port => &mut self.setup_port,
-                            SetupField::ApiTokenId => &mut self.setup_token_id,
-                            SetupField::ApiTokenSecret => &mut self.setup_token_secret,
-                            SetupField::VerifySsl => return,
-                        };
-                        input.handle(tui_input::InputRequest::DeletePrevChar);
+                        self.edit_setup_input(tui_input::InputRequest::DeletePrevChar);
                     }
                     KeyCode::Delete => {
-                        let input = match self.setup_field {
-                            SetupField::Name => &mut self.setup_name,
-                            SetupField::Host => &mut self.setup_host,
-                            SetupField::Port => &mut self.setup_port,
-                            SetupField::ApiTokenId => &mut self.setup_token_id,
-                            SetupField::ApiTokenSecret => &mut self.setup_token_secret,
-                            SetupField::VerifySsl => return,
-                        };
-                        input.handle(tui_input::InputRequest::DeleteNextChar);
+                        self.edit_setup_input(tui_input::InputRequest::DeleteNextChar);
                     }
                     KeyCode::Left => {
-                        let input = match self.setup_field {
-                            SetupField::Name => &mut self.setup_name,
-                            SetupField::Host => &mut self.setup_host,
-                            SetupField::Port => &mut self.setup_port,
-                            SetupField::ApiTokenId => &mut self.setup_token_id,
-                            SetupField::ApiTokenSecret => &mut self.setup_token_secret,
-                            SetupField::VerifySsl => return,
-                        };
-                        input.handle(tui_input::InputRequest::GoToPrevChar);
+                        if let Some(input) = self.setup_input_mut() {
+                            input.handle(tui_input::InputRequest::GoToPrevChar);
+                        }
                     }
                     KeyCode::Right => {
-                        let input = match self.setup_field {
-                            SetupField::Name => &mut self.setup_name,
-                            SetupField::Host => &mut self.setup_host,
-                            SetupField::Port => &mut self.setup_port,
-                            SetupField::ApiTokenId => &mut self.setup_token_id,
-                            SetupField::ApiTokenSecret => &mut self.setup_token_secret,
-                            SetupField::VerifySsl => return,
-                        };
-                        input.handle(tui_input::InputRequest::GoToNextChar);
+                        if let Some(input) = self.setup_input_mut() {
+                            input.handle(tui_input::InputRequest::GoToNextChar);
+                        }
                     }
                     _ => {}
                 }
@@ -0,0 +1,235 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::config::{AuthMode, ProxmoxHost};
+use crate::proxmox::ProxmoxClient;
+
+// Proxmox tickets are valid for two hours
+const TICKET_LIFETIME: Duration = Duration::from_secs(2 * 60 * 60);
+// Renew well before the ticket runs out
+pub const RENEW_AFTER: Duration = Duration::from_secs(60 * 60);
+
+/// A logged-in ticket session
+#[derive(Debug, Clone)]
+pub struct Ticket {
+    pub username: String,
+    pub ticket: String,
+    pub csrf_token: String,
+    pub issued: Instant,
+}
+
+impl Ticket {
+    pub fn is_valid(&self) -> bool {
+        // Leave a minute of slack for requests that are already in flight
+        self.issued.elapsed() + Duration::from_secs(60) < TICKET_LIFETIME
+    }
+
+    pub fn needs_renewal(&self) -> bool {
+        self.issued.elapsed() >= RENEW_AFTER
+    }
+}
+
+/// Result of posting credentials to /access/ticket
+pub enum LoginStep {
+    Done(Ticket),
+    // Password accepted, a TOTP code is needed to finish; holds the partial ticket
+    NeedTotp { challenge: String },
+}
+
+/// Body of a /access/ticket response
+#[derive(Debug, Deserialize)]
+pub struct TicketResponse {
+    pub username: String,
+    pub ticket: String,
+    #[serde(rename = "CSRFPreventionToken")]
+    pub csrf_token: Option<String>,
+    #[serde(rename = "NeedTFA", default)]
+    pub need_tfa: u8,
+}
+
+impl TicketResponse {
+    pub fn into_step(self) -> LoginStep {
+        if self.need_tfa != 0 {
+            return LoginStep::NeedTotp { challenge: self.ticket };
+        }
+
+        LoginStep::Done(Ticket {
+            username: self.username,
+            ticket: self.ticket,
+            csrf_token: self.csrf_token.unwrap_or_default(),
+            issued: Instant::now(),
+        })
+    }
+}
+
+/// Which of a config entry's credentials a request uses
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Credential {
+    // Reads, i.e. everything the dashboard does on its own
+    Viewer,
+    // Mutating requests; the same as Viewer unless the entry has operator credentials
+    Operator,
+}
+
+impl Credential {
+    /// Session store key for this credential of config entry `entry`
+    pub fn session_key(&self, entry: &str) -> String {
+        match self {
+            Credential::Viewer => entry.to_string(),
+            Credential::Operator => format!("{} (operator)", entry),
+        }
+    }
+}
+
+/// Tickets of the config entries that use ticket auth, keyed by session key.
+/// Shared between the UI and every client so a login or renewal is seen by all
+/// of them. Passwords are never kept here.
+#[derive(Clone, Default)]
+pub struct SessionStore {
+    tickets: Arc<Mutex<HashMap<String, Ticket>>>,
+}
+
+impl SessionStore {
+    pub fn get(&self, entry: &str) -> Option<Ticket> {
+        self.tickets.lock().ok()?.get(entry).cloned()
+    }
+
+    pub fn set(&self, entry: &str, ticket: Ticket) {
+        if let Ok(mut tickets) = self.tickets.lock() {
+            tickets.insert(entry.to_string(), ticket);
+        }
+    }
+
+    pub fn is_logged_in(&self, entry: &str) -> bool {
+        self.get(entry).is_some_and(|t| t.is_valid())
+    }
+}
+
+/// Returned for requests against a ticket-auth entry nobody has logged in to
+#[derive(Debug)]
+pub struct NotLoggedIn;
+
+impl std::fmt::Display for NotLoggedIn {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Not logged in (press l to log in)")
+    }
+}
+
+impl std::error::Error for NotLoggedIn {}
+
+/// Name of the API token the setup wizard creates
+pub const TOKEN_NAME: &str = "proxmon";
+
+/// Permissions given to a token created by the setup wizard
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TokenRole {
+    // Read-only access to everything proxmon shows
+    ReadOnly,
+    // Read-only plus managing guests: power, console, snapshots, backups and
+    // restores, migration, cloning and creating containers
+    Operator,
+    // No privilege separation: the token can do whatever the user can
+    SameAsUser,
+}
+
+impl TokenRole {
+    pub fn label(&self) -> &str {
+        match self {
+            TokenRole::ReadOnly => "Read-only (PVEAuditor on /)",
+            TokenRole::Operator => "Operator (PVEAuditor + PVEVMAdmin + PVEDatastoreUser on /)",
+            TokenRole::SameAsUser => "Same permissions as the user",
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            TokenRole::ReadOnly => TokenRole::Operator,
+            TokenRole::Operator => TokenRole::SameAsUser,
+            TokenRole::SameAsUser => TokenRole::ReadOnly,
+        }
+    }
+
+    /// Roles to grant on `/`, None when the token shares the user's permissions
+    fn roles(&self) -> Option<&str> {
+        match self {
+            TokenRole::ReadOnly => Some("PVEAuditor"),
+            // PVEDatastoreUser lets backups, restores and new disks use storage
+            TokenRole::Operator => Some("PVEAuditor,PVEVMAdmin,PVEDatastoreUser"),
+            TokenRole::SameAsUser => None,
+        }
+    }
+}
+
+/// Log in with a password, create a `proxmon` API token for the user and grant it
+/// `role`. Returns `pve_host` rewritten to use the new token; the password is only
+/// used for the login and dropped afterwards.
+pub async fn bootstrap_token(
+    mut pve_host: ProxmoxHost,
+    password: String,
+    totp: String,
+    role: TokenRole,
+) -> Result<ProxmoxHost> {
+    pve_host.auth = AuthMode::Ticket;
+    let client = ProxmoxClient::new(&pve_host, &SessionStore::default())?;
+
+    let mut step = client.login(Credential::Viewer, &password).await?;
+    drop(password);
+    if let LoginStep::NeedTotp { challenge } = step {
+        if totp.trim().is_empty() {
+            anyhow::bail!("This account has a second factor, enter the TOTP code");
+        }
+        step = client.login_totp(Credential::Viewer, &challenge, &totp).await?;
+    }
+    if let LoginStep::NeedTotp { .. } = step {
+        anyhow::bail!("Proxmox asked for another second factor, which proxmon doesn't support");
+    }
+
+    let userid = pve_host.user_at_realm();
+    // A privilege separated token starts without any permissions of its own
+    let token = client
+        .create_api_token(&userid, TOKEN_NAME, role.roles().is_some())
+        .await?;
+    if let Some(roles) = role.roles() {
+        client
+            .grant_token_role("/", roles, &token.full_tokenid)
+            .await
+            .with_context(|| format!("Token {} was created but granting {} failed", token.full_tokenid, roles))?;
+    }
+
+    pve_host.auth = AuthMode::Token;
+    pve_host.username = String::new();
+    pve_host.realm = None;
+    pve_host.api_token_id = token.full_tokenid;
+    pve_host.api_token_secret = token.value;
+    Ok(pve_host)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ticket_response_needing_totp() {
+        let response: TicketResponse = serde_json::from_str(
+            r#"{"username":"alice@ldap","ticket":"PVE:!tfa!challenge","NeedTFA":1}"#,
+        )
+        .unwrap();
+        assert!(matches!(response.into_step(), LoginStep::NeedTotp { challenge } if challenge == "PVE:!tfa!challenge"));
+
+        let response: TicketResponse = serde_json::from_str(
+            r#"{"username":"alice@ldap","ticket":"PVE:alice@ldap:1234","CSRFPreventionToken":"1234:abc"}"#,
+        )
+        .unwrap();
+        match response.into_step() {
+            LoginStep::Done(ticket) => {
+                assert_eq!(ticket.csrf_token, "1234:abc");
+                assert!(ticket.is_valid());
+                assert!(!ticket.needs_renewal());
+            }
+            LoginStep::NeedTotp { .. } => panic!("expected a full ticket"),
+        }
+    }
+}
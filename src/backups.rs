@@ -0,0 +1,122 @@
+use serde::Deserialize;
+
+/// How vzdump keeps the guest consistent while it is backed up
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BackupMode {
+    Snapshot,
+    Suspend,
+    Stop,
+}
+
+impl BackupMode {
+    pub fn as_str(&self) -> &str {
+        match self {
+            BackupMode::Snapshot => "snapshot",
+            BackupMode::Suspend => "suspend",
+            BackupMode::Stop => "stop",
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            BackupMode::Snapshot => BackupMode::Suspend,
+            BackupMode::Suspend => BackupMode::Stop,
+            BackupMode::Stop => BackupMode::Snapshot,
+        }
+    }
+}
+
+/// Compression of the vzdump archive
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Compression {
+    Zstd,
+    Lzo,
+    Gzip,
+    None,
+}
+
+impl Compression {
+    /// Value of vzdump's `compress` parameter
+    pub fn as_str(&self) -> &str {
+        match self {
+            Compression::Zstd => "zstd",
+            Compression::Lzo => "lzo",
+            Compression::Gzip => "gzip",
+            Compression::None => "0",
+        }
+    }
+
+    pub fn label(&self) -> &str {
+        match self {
+            Compression::None => "none",
+            other => other.as_str(),
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            Compression::Zstd => Compression::Lzo,
+            Compression::Lzo => Compression::Gzip,
+            Compression::Gzip => Compression::None,
+            Compression::None => Compression::Zstd,
+        }
+    }
+}
+
+/// One backup archive, from /nodes/{node}/storage/{storage}/content?content=backup
+#[derive(Debug, Clone, Deserialize)]
+pub struct Backup {
+    // Volume ID, e.g. "local:backup/vzdump-qemu-101-2024_05_01-02_00_03.vma.zst"
+    pub volid: String,
+    #[serde(default)]
+    pub size: u64,
+    // Unix time the archive was created
+    #[serde(default)]
+    pub ctime: u64,
+    pub vmid: Option<u32>,
+    #[serde(default)]
+    pub notes: String,
+}
+
+impl Backup {
+    /// Storage part of the volume ID
+    pub fn storage(&self) -> &str {
+        self.volid.split_once(':').map(|(storage, _)| storage).unwrap_or_default()
+    }
+
+    /// Archive file name without the storage and directory
+    pub fn file_name(&self) -> &str {
+        let path = self.volid.split_once(':').map(|(_, path)| path).unwrap_or(&self.volid);
+        path.rsplit('/').next().unwrap_or(path)
+    }
+}
+
+/// Backups of one guest, newest first
+pub fn for_guest(backups: Vec<Backup>, vmid: u32) -> Vec<Backup> {
+    let mut backups: Vec<Backup> = backups.into_iter().filter(|b| b.vmid == Some(vmid)).collect();
+    backups.sort_by_key(|b| std::cmp::Reverse(b.ctime));
+    backups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backups_for_guest_newest_first() {
+        let backups: Vec<Backup> = serde_json::from_str(
+            r#"[
+                {"volid": "local:backup/vzdump-qemu-101-2024_05_01-02_00_03.vma.zst", "size": 1024, "ctime": 100, "vmid": 101},
+                {"volid": "pbs:backup/vm/101/2024-05-02T02:00:00Z", "size": 2048, "ctime": 200, "vmid": 101},
+                {"volid": "local:backup/vzdump-lxc-102-2024_05_01-02_10_00.tar.zst", "size": 512, "ctime": 150, "vmid": 102}
+            ]"#,
+        )
+        .unwrap();
+
+        let backups = for_guest(backups, 101);
+        assert_eq!(backups.len(), 2);
+        assert_eq!(backups[0].storage(), "pbs");
+        assert_eq!(backups[0].file_name(), "2024-05-02T02:00:00Z");
+        assert_eq!(backups[1].file_name(), "vzdump-qemu-101-2024_05_01-02_00_03.vma.zst");
+    }
+}
@@ -0,0 +1,111 @@
+use crate::auth::SessionStore;
+use crate::config::ProxmoxHost;
+use crate::proxmox::ProxmoxClient;
+
+/// One or more configured entries that point at the same Proxmox cluster.
+/// Guests are fetched once per logical cluster; the extra entries act as
+/// failover endpoints.
+#[derive(Clone)]
+pub struct LogicalCluster {
+    // Name of the first matching entry in config order, used as the cluster's identity
+    pub name: String,
+    // Config entry names that belong to this cluster, in config order
+    pub members: Vec<String>,
+    // Cluster name as reported by /cluster/status (None for standalone nodes)
+    pub pve_cluster: Option<String>,
+    pub client: ProxmoxClient,
+}
+
+/// Build clients for every configured entry and merge the entries that report
+/// the same cluster name.
+///
+/// An entry that is down right now lands in the group of the cluster name saved
+/// in its `pve_cluster` on an earlier run, instead of showing up as a separate
+/// (failed) cluster, and its addresses stay failover targets for the group. `preferred` lists the endpoints
+/// that answered last time; they are tried first.
+pub async fn discover(
+    pve_hosts: &[ProxmoxHost],
+    preferred: &[String],
+    sessions: &SessionStore,
+) -> (Vec<LogicalCluster>, Vec<(String, anyhow::Error)>) {
+    let lookups = pve_hosts.iter().map(|pve_host| async move {
+        let mut client = ProxmoxClient::new(pve_host, sessions)?;
+        // Also applied to the final clients below, which know every endpoint
+        client.prefer(preferred);
+
+        let pve_cluster = match client.cluster_info().await {
+            Ok(info) => {
+                if pve_host.discover_endpoints {
+                    client.add_discovered(pve_host, &info.nodes);
+                }
+                info.name
+            }
+            Err(_) => pve_host.pve_cluster.clone(),
+        };
+        Ok::<_, anyhow::Error>((client, pve_cluster))
+    });
+    let results = futures::future::join_all(lookups).await;
+
+    let mut clusters: Vec<LogicalCluster> = Vec::new();
+    let mut failures = Vec::new();
+
+    for (pve_host, result) in pve_hosts.iter().zip(results) {
+        let (client, pve_cluster) = match result {
+            Ok(found) => found,
+            Err(e) => {
+                failures.push((pve_host.name.clone(), e.context("Error creating client")));
+                continue;
+            }
+        };
+
+        let existing = pve_cluster
+            .as_ref()
+            .and_then(|name| clusters.iter_mut().find(|c| c.pve_cluster.as_ref() == Some(name)));
+
+        match existing {
+            Some(cluster) => {
+                cluster.members.push(pve_host.name.clone());
+                cluster.client.merge(client);
+            }
+            None => clusters.push(LogicalCluster {
+                name: pve_host.name.clone(),
+                members: vec![pve_host.name.clone()],
+                pve_cluster,
+                client,
+            }),
+        }
+    }
+
+    // Discovered and merged endpoints only exist now
+    for cluster in &clusters {
+        cluster.client.prefer(preferred);
+    }
+
+    (clusters, failures)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_unreachable_entries_join_their_saved_cluster() {
+        // Nothing listens on port 1, so both entries fail right away
+        let entry = |name: &str, host: &str| ProxmoxHost {
+            name: name.to_string(),
+            host: host.to_string(),
+            port: 1,
+            api_token_id: "monitor@pve!proxmon".to_string(),
+            api_token_secret: "secret".to_string(),
+            pve_cluster: Some("lab".to_string()),
+            ..Default::default()
+        };
+        let pve_hosts = vec![entry("pve1", "127.0.0.1"), entry("pve2", "127.0.0.2")];
+
+        let (clusters, failures) = discover(&pve_hosts, &[], &SessionStore::default()).await;
+        assert!(failures.is_empty());
+        assert_eq!(clusters.len(), 1);
+        assert_eq!(clusters[0].name, "pve1");
+        assert_eq!(clusters[0].members, vec!["pve1", "pve2"]);
+    }
+}
@@ -1,8 +1,12 @@
+use age::secrecy::SecretString;
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 use std::fs;
+use std::io::{Read, Write};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+use crate::proxmox::{Host, HostId};
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Config {
     pub proxmox_hosts: Vec<ProxmoxHost>,
     #[serde(default)]
@@ -11,6 +15,18 @@ pub struct Config {
     pub ip_overrides: Vec<IpOverride>,
     #[serde(default)]
     pub ansible_defaults: AnsibleDefaults,
+    // Seconds between automatic background refreshes (0 disables)
+    #[serde(default)]
+    pub refresh_interval: u64,
+    // Refuse every request that would change something on a cluster (like --read-only)
+    #[serde(default)]
+    pub read_only: bool,
+    // Guests that need extra care before destructive actions
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub protected: Vec<ProtectedRule>,
+    // Set when the file was loaded encrypted, so saving encrypts it again
+    #[serde(skip)]
+    pub passphrase: Option<SecretString>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -19,10 +35,192 @@ pub struct ProxmoxHost {
     pub host: String,
     #[serde(default = "default_port")]
     pub port: u16,
+    // Further nodes of the same cluster ("host" or "host:port"), tried in order
+    // when `host` does not answer
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub endpoints: Vec<String>,
+    // Also fail over to node addresses reported by /cluster/status
+    #[serde(default)]
+    pub discover_endpoints: bool,
+    // Proxmox cluster name learned from /cluster/status and saved by proxmon, so
+    // entries of one cluster stay merged even if this one is down at startup
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pve_cluster: Option<String>,
+    #[serde(default, skip_serializing_if = "AuthMode::is_token")]
+    pub auth: AuthMode,
+    // Ticket auth only: user name and realm; the password is asked for at startup
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub username: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub realm: Option<String>,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub api_token_id: String,
+    // Either the secret itself or a reference: env:VAR, file:/path or cmd:command
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub api_token_secret: String,
+    // Secret behind a reference in api_token_secret, resolved at load time. Never
+    // serialized, so saving the config keeps the reference.
+    #[serde(skip)]
+    pub resolved_secret: Option<std::result::Result<String, String>>,
+    // Used instead of the credentials above for mutating requests (power actions,
+    // snapshots, migrations, ...), so those can stay read-only
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub operator_token_id: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub operator_token_secret: String,
+    #[serde(skip)]
+    pub resolved_operator_secret: Option<std::result::Result<String, String>>,
+    // Or a user whose password is asked for when the first mutating request is made
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub operator_username: String,
     #[serde(default)]
     pub verify_ssl: bool,
+    // SHA-256 fingerprint of the pinned (usually self-signed) certificate
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fingerprint: Option<String>,
+    // PEM file with extra trusted root certificates (e.g. an internal CA)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ca_file: Option<String>,
+    // http://, https:// or socks5:// proxy to reach this cluster through
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<String>,
+    // Seconds to wait for a connection, and for a whole request
+    #[serde(default = "default_connect_timeout")]
+    pub connect_timeout: u64,
+    #[serde(default = "default_request_timeout")]
+    pub request_timeout: u64,
+    // Maximum number of API requests in flight against this cluster
+    #[serde(default = "default_max_concurrency")]
+    pub max_concurrency: usize,
+}
+
+/// How proxmon authenticates against a Proxmox host
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuthMode {
+    // API token from api_token_id/api_token_secret
+    #[default]
+    Token,
+    // /access/ticket login with username, realm, password and optional TOTP
+    Ticket,
+}
+
+impl AuthMode {
+    fn is_token(&self) -> bool {
+        *self == AuthMode::Token
+    }
+}
+
+impl ProxmoxHost {
+    /// Resolve secret references in api_token_secret and operator_token_secret.
+    /// A secret that can't be resolved only breaks its own cluster, so the error
+    /// is kept for later.
+    pub fn resolve_secret(&mut self) {
+        let resolve = |value: &str| match resolve_secret(value) {
+            Ok(Some(secret)) => Some(Ok(secret)),
+            Ok(None) => None,
+            Err(e) => Some(Err(format!("{:#}", e))),
+        };
+        self.resolved_secret = resolve(&self.api_token_secret);
+        self.resolved_operator_secret = resolve(&self.operator_token_secret);
+    }
+
+    /// The API token secret, with an env:/file:/cmd: reference resolved
+    pub fn token_secret(&self) -> Result<String> {
+        match &self.resolved_secret {
+            Some(Ok(secret)) => Ok(secret.clone()),
+            Some(Err(e)) => anyhow::bail!("{}", e),
+            None => Ok(self.api_token_secret.clone()),
+        }
+    }
+
+    /// The operator token secret, with an env:/file:/cmd: reference resolved
+    pub fn operator_token_secret(&self) -> Result<String> {
+        match &self.resolved_operator_secret {
+            Some(Ok(secret)) => Ok(secret.clone()),
+            Some(Err(e)) => anyhow::bail!("Operator token: {}", e),
+            None => Ok(self.operator_token_secret.clone()),
+        }
+    }
+
+    /// Login name including the realm, e.g. "alice@ldap" (realm defaults to pam)
+    pub fn user_at_realm(&self) -> String {
+        self.with_realm(&self.username)
+    }
+
+    /// Operator login name including the realm
+    pub fn operator_user_at_realm(&self) -> String {
+        self.with_realm(&self.operator_username)
+    }
+
+    fn with_realm(&self, username: &str) -> String {
+        if username.contains('@') {
+            return username.to_string();
+        }
+        format!("{}@{}", username, self.realm.as_deref().unwrap_or("pam"))
+    }
+
+    /// Every configured address as (host, port), starting with `host`
+    pub fn addresses(&self) -> Vec<(String, u16)> {
+        let mut addresses = vec![(self.host.clone(), self.port)];
+        for endpoint in &self.endpoints {
+            let address = parse_endpoint(endpoint, self.port);
+            if !address.0.is_empty() && !addresses.contains(&address) {
+                addresses.push(address);
+            }
+        }
+        addresses
+    }
+}
+
+/// Split "host", "host:port" or "[v6addr]:port" into host and port
+pub fn parse_endpoint(endpoint: &str, default_port: u16) -> (String, u16) {
+    let endpoint = endpoint.trim();
+
+    if let Some(rest) = endpoint.strip_prefix('[') {
+        if let Some((host, port)) = rest.split_once(']') {
+            let port = port.strip_prefix(':').and_then(|p| p.parse().ok()).unwrap_or(default_port);
+            return (host.to_string(), port);
+        }
+    }
+
+    match endpoint.split_once(':') {
+        // More than one colon is a bare IPv6 address
+        Some((host, port)) if !port.contains(':') => {
+            (host.to_string(), port.parse().unwrap_or(default_port))
+        }
+        _ => (endpoint.to_string(), default_port),
+    }
+}
+
+impl Default for ProxmoxHost {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            host: String::new(),
+            port: default_port(),
+            endpoints: Vec::new(),
+            discover_endpoints: false,
+            pve_cluster: None,
+            auth: AuthMode::Token,
+            username: String::new(),
+            realm: None,
+            api_token_id: String::new(),
+            api_token_secret: String::new(),
+            resolved_secret: None,
+            operator_token_id: String::new(),
+            operator_token_secret: String::new(),
+            resolved_operator_secret: None,
+            operator_username: String::new(),
+            verify_ssl: false,
+            fingerprint: None,
+            ca_file: None,
+            proxy: None,
+            connect_timeout: default_connect_timeout(),
+            request_timeout: default_request_timeout(),
+            max_concurrency: default_max_concurrency(),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -38,6 +236,139 @@ pub struct ManualHost {
 pub struct IpOverride {
     pub name: String,
     pub ip: String,
+    // Cluster and vmid pin the override to one guest. Older entries only carry
+    // a name and apply to every host with that name.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cluster: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vmid: Option<u32>,
+}
+
+impl IpOverride {
+    pub fn for_host(id: &HostId, name: &str, ip: String) -> Self {
+        let (cluster, vmid) = match id {
+            HostId::Guest { cluster, vmid } => (Some(cluster.clone()), Some(*vmid)),
+            HostId::Manual(_) => (None, None),
+        };
+        Self {
+            name: name.to_string(),
+            ip,
+            cluster,
+            vmid,
+        }
+    }
+
+    /// Whether this entry is pinned to exactly this host
+    pub fn matches_id(&self, id: &HostId) -> bool {
+        match (id, &self.cluster, self.vmid) {
+            (HostId::Guest { cluster, vmid }, Some(c), Some(v)) => cluster == c && *vmid == v,
+            (HostId::Manual(name), None, None) => *name == self.name,
+            _ => false,
+        }
+    }
+
+    /// Whether this is a legacy name-only entry for the given name
+    pub fn matches_name(&self, name: &str) -> bool {
+        self.cluster.is_none() && self.vmid.is_none() && self.name == name
+    }
+}
+
+/// Guests matched by every criterion that is set: a name glob (`*` and `?`), a
+/// Proxmox tag and/or a vmid or vmid range
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProtectedRule {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vmid: Option<VmidMatch>,
+    #[serde(default)]
+    pub protection: Protection,
+}
+
+/// What a protected rule does to destructive actions
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Protection {
+    // The guest's name has to be typed to confirm
+    #[default]
+    Confirm,
+    // Never allowed from proxmon
+    Block,
+}
+
+/// A single vmid (`105`) or an inclusive range (`"100-199"`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum VmidMatch {
+    One(u32),
+    Range(String),
+}
+
+impl VmidMatch {
+    fn matches(&self, vmid: u32) -> bool {
+        match self {
+            VmidMatch::One(id) => *id == vmid,
+            VmidMatch::Range(range) => match range.split_once('-') {
+                Some((first, last)) => match (first.trim().parse::<u32>(), last.trim().parse::<u32>()) {
+                    (Ok(first), Ok(last)) => (first..=last).contains(&vmid),
+                    _ => false,
+                },
+                None => range.trim().parse() == Ok(vmid),
+            },
+        }
+    }
+}
+
+impl ProtectedRule {
+    /// A rule without any criteria matches nothing
+    pub fn matches(&self, host: &Host) -> bool {
+        if self.name.is_none() && self.tag.is_none() && self.vmid.is_none() {
+            return false;
+        }
+
+        let name = self
+            .name
+            .as_ref()
+            .is_none_or(|pattern| glob_match(&pattern.to_lowercase(), &host.name.to_lowercase()));
+        let tag = self
+            .tag
+            .as_ref()
+            .is_none_or(|tag| host.tags.iter().any(|t| t.eq_ignore_ascii_case(tag)));
+        let vmid = self
+            .vmid
+            .as_ref()
+            .is_none_or(|range| host.vmid.is_some_and(|id| range.matches(id)));
+        name && tag && vmid
+    }
+}
+
+/// Match `text` against a pattern where `*` is any run of characters and `?` any one
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // Position of the last `*` and the text position it currently stands for
+    let mut star: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, t));
+            p += 1;
+        } else if let Some((star_p, star_t)) = star {
+            // Let the last `*` swallow one more character
+            p = star_p + 1;
+            t = star_t + 1;
+            star = Some((star_p, star_t + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -65,6 +396,18 @@ fn default_port() -> u16 {
     8006
 }
 
+fn default_max_concurrency() -> usize {
+    8
+}
+
+fn default_connect_timeout() -> u64 {
+    5
+}
+
+fn default_request_timeout() -> u64 {
+    30
+}
+
 fn default_python_interpreter() -> String {
     "/usr/bin/python3".to_string()
 }
@@ -77,26 +420,288 @@ fn default_become_method() -> String {
     "sudo".to_string()
 }
 
-impl Default for Config {
-    fn default() -> Self {
-        Self {
-            proxmox_hosts: Vec::new(),
-            manual_hosts: Vec::new(),
-            ip_overrides: Vec::new(),
-            ansible_defaults: AnsibleDefaults::default(),
-        }
+impl Config {
+    /// IP override for a host, preferring entries pinned to its identity over
+    /// legacy name-only entries
+    pub fn ip_override_for(&self, id: &HostId, name: &str) -> Option<&IpOverride> {
+        self.ip_overrides
+            .iter()
+            .find(|o| o.matches_id(id))
+            .or_else(|| self.ip_overrides.iter().find(|o| o.matches_name(name)))
     }
-}
 
-impl Config {
-    pub fn load(path: &str) -> Result<Self> {
-        let contents = fs::read_to_string(path)
+    /// Load the config at `path`. For an encrypted file `ask_passphrase` is called
+    /// to get the passphrase, which is kept so `save` can encrypt it again.
+    pub fn load(path: &str, ask_passphrase: impl FnOnce() -> Result<SecretString>) -> Result<Self> {
+        let raw = fs::read(path)
             .context(format!("Failed to read config file: {}", path))?;
 
-        let config: Config = serde_yaml::from_str(&contents)
+        let (contents, passphrase) = if is_encrypted(&raw) {
+            let passphrase = ask_passphrase()?;
+            (decrypt(&raw, &passphrase)?, Some(passphrase))
+        } else {
+            (String::from_utf8(raw).context("Config file is not valid UTF-8")?, None)
+        };
+
+        let mut config: Config = serde_yaml::from_str(&contents)
             .context("Failed to parse config file")?;
+        config.passphrase = passphrase;
+
+        for pve_host in &mut config.proxmox_hosts {
+            pve_host.resolve_secret();
+        }
 
         Ok(config)
     }
+
+    /// Strictest protection of the rules matching `host`
+    pub fn protection_for(&self, host: &Host) -> Option<Protection> {
+        self.protected
+            .iter()
+            .filter(|rule| rule.matches(host))
+            .map(|rule| rule.protection)
+            .max()
+    }
+
+    /// Write the config to `path`, encrypted if it was loaded encrypted
+    pub fn save(&self, path: &str) -> Result<()> {
+        let yaml = serde_yaml::to_string(self)?;
+        let contents = match &self.passphrase {
+            Some(passphrase) => encrypt(&yaml, passphrase)?,
+            None => yaml,
+        };
+        write_private(path, &contents)?;
+        Ok(())
+    }
+}
+
+/// Whether `contents` is an age file, armored or binary
+pub fn is_encrypted(contents: &[u8]) -> bool {
+    contents.starts_with(b"age-encryption.org/v1") || contents.starts_with(b"-----BEGIN AGE ENCRYPTED FILE-----")
+}
+
+/// Encrypt `plaintext` with a passphrase into an ASCII-armored age file
+pub fn encrypt(plaintext: &str, passphrase: &SecretString) -> Result<String> {
+    let encryptor = age::Encryptor::with_user_passphrase(passphrase.clone());
+
+    let mut armored = Vec::new();
+    let armor = age::armor::ArmoredWriter::wrap_output(&mut armored, age::armor::Format::AsciiArmor)?;
+    let mut writer = encryptor.wrap_output(armor)?;
+    writer.write_all(plaintext.as_bytes())?;
+    writer.finish()?.finish()?;
+
+    Ok(String::from_utf8(armored)?)
+}
+
+/// Decrypt a passphrase-encrypted age file, armored or binary
+pub fn decrypt(contents: &[u8], passphrase: &SecretString) -> Result<String> {
+    let decryptor = age::Decryptor::new(age::armor::ArmoredReader::new(contents))
+        .context("Config file is not a valid age file")?;
+    if !decryptor.is_scrypt() {
+        anyhow::bail!("Config file is encrypted to a key, not a passphrase");
+    }
+
+    let identity = age::scrypt::Identity::new(passphrase.clone());
+    let mut reader = decryptor
+        .decrypt(std::iter::once(&identity as &dyn age::Identity))
+        .map_err(|e| match e {
+            age::DecryptError::DecryptionFailed | age::DecryptError::NoMatchingKeys => {
+                anyhow::anyhow!("Wrong passphrase for the config file")
+            }
+            e => anyhow::anyhow!("Failed to decrypt config file: {}", e),
+        })?;
+
+    let mut plaintext = String::new();
+    reader
+        .read_to_string(&mut plaintext)
+        .context("Failed to decrypt config file")?;
+    Ok(plaintext)
 }
 
+/// Look up an `env:VAR`, `file:/path` or `cmd:command` secret reference.
+/// Returns None when `value` is a plain secret.
+pub fn resolve_secret(value: &str) -> Result<Option<String>> {
+    let secret = if let Some(var) = value.strip_prefix("env:") {
+        std::env::var(var).with_context(|| format!("Environment variable {} is not set", var))?
+    } else if let Some(path) = value.strip_prefix("file:") {
+        let path = match path.strip_prefix("~/") {
+            Some(rest) => dirs::home_dir().map(|h| h.join(rest)).unwrap_or_else(|| path.into()),
+            None => path.into(),
+        };
+        fs::read_to_string(&path).with_context(|| format!("Failed to read secret file {}", path.display()))?
+    } else if let Some(command) = value.strip_prefix("cmd:") {
+        // No terminal access: the TUI may own it, so a prompting command fails instead of hanging
+        let output = std::process::Command::new("sh")
+            .arg("-c")
+            .arg(command)
+            .stdin(std::process::Stdio::null())
+            .output()
+            .with_context(|| format!("Failed to run secret command '{}'", command))?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            match stderr.trim() {
+                "" => anyhow::bail!("Secret command '{}' failed with {}", command, output.status),
+                stderr => anyhow::bail!("Secret command '{}' failed with {}: {}", command, output.status, stderr),
+            }
+        }
+        String::from_utf8(output.stdout).context("Secret command printed invalid UTF-8")?
+    } else {
+        return Ok(None);
+    };
+
+    // Files and commands usually end with a newline
+    Ok(Some(secret.trim().to_string()))
+}
+
+/// Write a config file that only the current user can read. Permissions are set
+/// when the file is created; an existing file keeps its mode.
+pub fn write_private(path: &str, contents: &str) -> std::io::Result<()> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options.open(path)?.write_all(contents.as_bytes())
+}
+
+/// Warning for a config file other users can read, since it holds API secrets
+pub fn permission_warning(path: &str) -> Option<String> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        let mode = fs::metadata(path).ok()?.permissions().mode();
+        if mode & 0o004 != 0 {
+            return Some(format!(
+                "Warning: {} is readable by other users (mode {:o}), run: chmod 600 {}",
+                path,
+                mode & 0o777,
+                path
+            ));
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_secret_references() {
+        // Set by cargo for every test binary
+        let name = std::env::var("CARGO_PKG_NAME").unwrap();
+        assert_eq!(resolve_secret("env:CARGO_PKG_NAME").unwrap(), Some(name));
+        assert_eq!(resolve_secret("cmd:echo from-cmd").unwrap().as_deref(), Some("from-cmd"));
+        assert!(resolve_secret("env:PROXMON_TEST_UNSET_SECRET").is_err());
+        assert!(resolve_secret("cmd:false").is_err());
+        let err = resolve_secret("cmd:echo locked >&2; exit 1").unwrap_err();
+        assert!(err.to_string().ends_with(": locked"));
+        assert_eq!(resolve_secret("5f2d-plain-secret").unwrap(), None);
+    }
+
+    #[test]
+    fn test_encrypt_round_trip() {
+        let passphrase = SecretString::from("correct horse".to_string());
+        let encrypted = encrypt("proxmox_hosts: []\n", &passphrase).unwrap();
+
+        assert!(is_encrypted(encrypted.as_bytes()));
+        assert!(!is_encrypted(b"proxmox_hosts: []\n"));
+        assert_eq!(decrypt(encrypted.as_bytes(), &passphrase).unwrap(), "proxmox_hosts: []\n");
+
+        let wrong = SecretString::from("battery staple".to_string());
+        let err = decrypt(encrypted.as_bytes(), &wrong).unwrap_err();
+        assert!(err.to_string().contains("Wrong passphrase"));
+    }
+
+    #[test]
+    fn test_addresses_parse_endpoints() {
+        let pve_host = ProxmoxHost {
+            host: "10.0.0.1".to_string(),
+            endpoints: vec![
+                "10.0.0.2".to_string(),
+                "pve3.lan:8007".to_string(),
+                "[fd00::4]:8006".to_string(),
+                "10.0.0.1:8006".to_string(),
+            ],
+            ..Default::default()
+        };
+
+        assert_eq!(
+            pve_host.addresses(),
+            vec![
+                ("10.0.0.1".to_string(), 8006),
+                ("10.0.0.2".to_string(), 8006),
+                ("pve3.lan".to_string(), 8007),
+                ("fd00::4".to_string(), 8006),
+            ]
+        );
+        assert_eq!(parse_endpoint("fd00::5", 8006), ("fd00::5".to_string(), 8006));
+    }
+
+    #[test]
+    fn test_ip_override_for_prefers_pinned_entries() {
+        let config: Config = serde_yaml::from_str(
+            r#"
+proxmox_hosts: []
+ip_overrides:
+  - name: "web"
+    ip: "10.0.0.1"
+  - name: "web"
+    ip: "10.0.0.2"
+    cluster: "vs02"
+    vmid: 100
+"#,
+        )
+        .unwrap();
+
+        let vs01 = HostId::Guest { cluster: "vs01".to_string(), vmid: 100 };
+        let vs02 = HostId::Guest { cluster: "vs02".to_string(), vmid: 100 };
+
+        // Legacy name-only entries still apply
+        assert_eq!(config.ip_override_for(&vs01, "web").unwrap().ip, "10.0.0.1");
+        // Pinned entries win and don't leak to same-named guests elsewhere
+        assert_eq!(config.ip_override_for(&vs02, "web").unwrap().ip, "10.0.0.2");
+        let other = HostId::Guest { cluster: "vs02".to_string(), vmid: 101 };
+        assert!(config.ip_override_for(&other, "db").is_none());
+    }
+
+    #[test]
+    fn test_protection_for_picks_strictest_rule() {
+        let config: Config = serde_yaml::from_str(
+            r#"
+proxmox_hosts: []
+protected:
+  - name: "prod-*"
+  - tag: database
+    protection: block
+  - vmid: "900-999"
+  - name: "*"
+    vmid: 42
+    protection: block
+"#,
+        )
+        .unwrap();
+
+        let guest = |name: &str, vmid: u32, tags: &[&str]| Host {
+            vmid: Some(vmid),
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            ..Host::from_manual(&ManualHost {
+                name: name.to_string(),
+                ip: String::new(),
+                host_type: "vm".to_string(),
+                ansible_user: String::new(),
+            })
+        };
+
+        assert_eq!(config.protection_for(&guest("PROD-web1", 100, &[])), Some(Protection::Confirm));
+        assert_eq!(config.protection_for(&guest("prod-db1", 101, &["database"])), Some(Protection::Block));
+        assert_eq!(config.protection_for(&guest("scratch", 950, &[])), Some(Protection::Confirm));
+        assert_eq!(config.protection_for(&guest("scratch", 42, &[])), Some(Protection::Block));
+        assert_eq!(config.protection_for(&guest("production", 100, &[])), None);
+    }
+}
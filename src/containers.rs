@@ -0,0 +1,125 @@
+use std::net::Ipv4Addr;
+
+use crate::proxmox::StorageInfo;
+
+/// How the container's eth0 gets its address
+#[derive(Debug, Clone, PartialEq)]
+pub enum Network {
+    Dhcp,
+    Static {
+        // Address with prefix length, e.g. 10.0.0.5/24
+        cidr: String,
+        gateway: Option<String>,
+    },
+}
+
+/// Everything needed to create a container with POST /nodes/{node}/lxc
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContainerSpec {
+    pub vmid: u32,
+    pub hostname: String,
+    // Volume ID of the OS template, e.g. "local:vztmpl/debian-12-standard_12.7-1_amd64.tar.zst"
+    pub ostemplate: String,
+    pub cores: u32,
+    pub memory_mb: u32,
+    pub rootfs_storage: String,
+    pub rootfs_gb: u32,
+    pub bridge: String,
+    pub network: Network,
+    pub ssh_key: Option<String>,
+    pub start: bool,
+}
+
+impl ContainerSpec {
+    /// Form parameters of the create request
+    pub fn params(&self) -> Vec<(&'static str, String)> {
+        let ip = match &self.network {
+            Network::Dhcp => "ip=dhcp".to_string(),
+            Network::Static { cidr, gateway: Some(gw) } => format!("ip={},gw={}", cidr, gw),
+            Network::Static { cidr, gateway: None } => format!("ip={}", cidr),
+        };
+
+        let mut params = vec![
+            ("vmid", self.vmid.to_string()),
+            ("hostname", self.hostname.clone()),
+            ("ostemplate", self.ostemplate.clone()),
+            ("cores", self.cores.to_string()),
+            ("memory", self.memory_mb.to_string()),
+            ("rootfs", format!("{}:{}", self.rootfs_storage, self.rootfs_gb)),
+            ("net0", format!("name=eth0,bridge={},{}", self.bridge, ip)),
+            // Throwaway containers don't need to run as root on the host
+            ("unprivileged", "1".to_string()),
+            ("start", if self.start { "1" } else { "0" }.to_string()),
+        ];
+        if let Some(key) = &self.ssh_key {
+            params.push(("ssh-public-keys", key.clone()));
+        }
+        params
+    }
+
+    /// Address of a static network setup, without the prefix length
+    pub fn static_ip(&self) -> Option<&str> {
+        match &self.network {
+            Network::Static { cidr, .. } => cidr.split('/').next(),
+            Network::Dhcp => None,
+        }
+    }
+}
+
+/// Templates and root disk storages a node offers for new containers
+#[derive(Debug, Clone, Default)]
+pub struct NodeOptions {
+    // Template volume IDs, sorted
+    pub templates: Vec<String>,
+    pub storages: Vec<StorageInfo>,
+}
+
+/// Template file name without storage and directory, for display
+pub fn template_name(volid: &str) -> &str {
+    volid.rsplit(['/', ':']).next().unwrap_or(volid)
+}
+
+/// Check an IPv4 address with prefix length, e.g. 10.0.0.5/24
+pub fn valid_cidr(cidr: &str) -> bool {
+    let Some((address, prefix)) = cidr.split_once('/') else {
+        return false;
+    };
+    address.parse::<Ipv4Addr>().is_ok() && prefix.parse::<u8>().is_ok_and(|p| (1..=32).contains(&p))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_static_network_params() {
+        let spec = ContainerSpec {
+            vmid: 120,
+            hostname: "scratch".to_string(),
+            ostemplate: "local:vztmpl/debian-12-standard_12.7-1_amd64.tar.zst".to_string(),
+            cores: 2,
+            memory_mb: 1024,
+            rootfs_storage: "local-lvm".to_string(),
+            rootfs_gb: 8,
+            bridge: "vmbr0".to_string(),
+            network: Network::Static {
+                cidr: "10.0.0.5/24".to_string(),
+                gateway: Some("10.0.0.1".to_string()),
+            },
+            ssh_key: None,
+            start: true,
+        };
+
+        let params = spec.params();
+        let value = |key: &str| params.iter().find(|(k, _)| *k == key).map(|(_, v)| v.as_str());
+        assert_eq!(value("rootfs"), Some("local-lvm:8"));
+        assert_eq!(value("net0"), Some("name=eth0,bridge=vmbr0,ip=10.0.0.5/24,gw=10.0.0.1"));
+        assert_eq!(value("ssh-public-keys"), None);
+        assert_eq!(spec.static_ip(), Some("10.0.0.5"));
+
+        assert_eq!(template_name(&spec.ostemplate), "debian-12-standard_12.7-1_amd64.tar.zst");
+        assert!(valid_cidr("10.0.0.5/24"));
+        assert!(!valid_cidr("10.0.0.5"));
+        assert!(!valid_cidr("10.0.0.300/24"));
+    }
+}
@@ -0,0 +1,176 @@
+use anyhow::Result;
+use futures::stream::{FuturesUnordered, StreamExt};
+use tokio::sync::mpsc;
+
+use crate::auth::{Credential, LoginStep, SessionStore};
+use crate::backups::Backup;
+use crate::cluster::{discover, LogicalCluster};
+use crate::config::ProxmoxHost;
+use crate::containers::NodeOptions;
+use crate::permissions::Permissions;
+use crate::proxmox::{ClusterStatus, ConnectionReport, GuestAction, Host, HostId, NodeInfo, StorageInfo};
+use crate::snapshots::{CountCache, Snapshot};
+use crate::tasks::TaskUpdate;
+
+pub type EventSender = mpsc::UnboundedSender<AppEvent>;
+
+/// Messages sent from background workers to the UI loop
+pub enum AppEvent {
+    /// Configured entries were grouped into logical clusters; `total` also counts
+    /// entries that could not be set up at all
+    ClustersResolved {
+        clusters: Vec<LogicalCluster>,
+        total: usize,
+    },
+    /// A cluster answered a refresh with its hosts and the privileges it was read with
+    HostsLoaded {
+        cluster: String,
+        hosts: Vec<Host>,
+        permissions: Option<Permissions>,
+    },
+    /// A cluster could not be queried during a refresh
+    ClusterFailed {
+        cluster: String,
+        status: ClusterStatus,
+        error: String,
+    },
+    /// Every cluster of the current refresh has answered
+    RefreshFinished,
+    /// Progress of a followed Proxmox task
+    Task(TaskUpdate),
+    /// A guest action finished (or failed)
+    ActionFinished(ActionOutcome),
+    /// Snapshot list of the guest shown in the snapshot panel
+    SnapshotsLoaded {
+        host: HostId,
+        result: Result<Vec<Snapshot>>,
+    },
+    /// Backups of the guest shown in the backup browser
+    BackupsLoaded {
+        host: HostId,
+        result: Result<Vec<Backup>>,
+    },
+    /// Backup storages of the node the backup form lists storages for
+    BackupStoragesLoaded {
+        node: String,
+        result: Result<Vec<StorageInfo>>,
+    },
+    /// Nodes of a cluster, for the form that asked for them
+    NodesLoaded {
+        form: Form,
+        cluster: String,
+        result: Result<Vec<NodeInfo>>,
+    },
+    /// Next free vmid of a cluster, suggested in the form that asked for it
+    NextVmidLoaded {
+        form: Form,
+        cluster: String,
+        result: Result<u32>,
+    },
+    /// OS templates and storages of the node picked in the new container wizard
+    ContainerOptionsLoaded {
+        node: String,
+        result: Result<NodeOptions>,
+    },
+    /// A container from the wizard was created (or not)
+    ContainerCreated(ContainerOutcome),
+    /// A ticket login (password or TOTP step) for a config entry finished
+    LoginFinished {
+        cluster: String,
+        credential: Credential,
+        result: Result<LoginStep>,
+    },
+    /// The setup wizard created an API token; carries the host entry to save
+    TokenCreated(Result<ProxmoxHost>),
+    /// Secret reference of a host about to be added in the Setup form was looked up
+    SetupSecretResolved(ProxmoxHost),
+    /// Certificate fingerprint of a host about to be added in the Setup form
+    SetupFingerprint(Result<String>),
+    /// Connection check of a host about to be added in the Setup form
+    SetupChecked {
+        host: ProxmoxHost,
+        result: Result<ConnectionReport>,
+    },
+}
+
+/// Form that asked for a node list or a free vmid
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Form {
+    Migrate,
+    Clone,
+    Restore,
+    NewContainer,
+}
+
+/// Result of a guest action, sent back from the background task that ran it
+pub struct ActionOutcome {
+    pub host: HostId,
+    pub host_name: String,
+    pub action: GuestAction,
+    // New guest status on success
+    pub result: Result<String>,
+}
+
+/// Result of creating a container, with what to remember about it
+pub struct ContainerOutcome {
+    pub cluster: String,
+    pub vmid: u32,
+    pub hostname: String,
+    // Static IP to save as an override once the container exists
+    pub ip_override: Option<String>,
+    pub result: Result<()>,
+}
+
+/// Group the configured entries into logical clusters, then query every cluster
+/// concurrently, reporting each one as soon as it answers
+pub fn spawn_refresh(
+    pve_hosts: Vec<ProxmoxHost>,
+    preferred: Vec<String>,
+    sessions: SessionStore,
+    snapshot_counts: CountCache,
+    tx: EventSender,
+) {
+    tokio::spawn(async move {
+        let (clusters, failures) = discover(&pve_hosts, &preferred, &sessions).await;
+
+        let _ = tx.send(AppEvent::ClustersResolved {
+            clusters: clusters.clone(),
+            total: clusters.len() + failures.len(),
+        });
+
+        for (cluster, e) in failures {
+            let _ = tx.send(AppEvent::ClusterFailed {
+                cluster,
+                status: ClusterStatus::from_error(&e),
+                error: format!("{:#}", e),
+            });
+        }
+
+        let snapshot_counts = &snapshot_counts;
+        let mut fetches: FuturesUnordered<_> = clusters
+            .into_iter()
+            .map(|cluster| async move {
+                let result = cluster.client.fetch_all_hosts(snapshot_counts).await;
+                (cluster.name, result)
+            })
+            .collect();
+
+        while let Some((cluster, result)) = fetches.next().await {
+            let event = match result {
+                Ok((hosts, permissions)) => AppEvent::HostsLoaded {
+                    cluster,
+                    hosts,
+                    permissions,
+                },
+                Err(e) => AppEvent::ClusterFailed {
+                    cluster,
+                    status: ClusterStatus::from_error(&e),
+                    error: format!("{:#}", e),
+                },
+            };
+            let _ = tx.send(event);
+        }
+
+        let _ = tx.send(AppEvent::RefreshFinished);
+    });
+}
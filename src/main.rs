@@ -1,12 +1,22 @@
 mod ansible;
 mod app;
+mod auth;
+mod backups;
+mod cluster;
 mod config;
+mod containers;
+mod events;
+mod permissions;
 mod proxmox;
+mod snapshots;
+mod tasks;
+mod tls;
 mod ui;
 
-use anyhow::Result;
+use age::secrecy::{ExposeSecret, SecretString};
+use anyhow::{Context, Result};
 use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event},
+    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEventKind, KeyModifiers},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
@@ -14,7 +24,7 @@ use ratatui::{
     backend::{Backend, CrosstermBackend},
     Terminal,
 };
-use std::io;
+use std::io::{self, Write};
 use std::time::Duration;
 
 use app::App;
@@ -36,46 +46,71 @@ async fn main() -> Result<()> {
             "config.yml".to_string()
         });
 
-    // Load configuration, create empty one if it doesn't exist
-    let (config, actual_config_path) = match Config::load(&config_path) {
-        Ok(cfg) => (cfg, config_path),
-        Err(_) => {
-            // Config doesn't exist, create it with defaults
-            eprintln!("No config found at {}", config_path);
-
-            // Determine the default config path
-            let default_path = if let Some(home_dir) = dirs::home_dir() {
-                home_dir.join(".config").join("proxmon").join("config.yml")
-            } else {
-                std::path::PathBuf::from("config.yml")
-            };
-
-            // Create the directory structure if it doesn't exist
-            if let Some(parent) = default_path.parent() {
-                if let Err(e) = std::fs::create_dir_all(parent) {
-                    eprintln!("Error: Failed to create config directory: {}", e);
-                    std::process::exit(1);
-                }
-            }
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("config") {
+        if let Err(e) = config_command(&config_path, args.get(1).map(String::as_str)) {
+            eprintln!("Error: {:#}", e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
 
-            // Create an empty config with defaults
-            let empty_config = Config::default();
-            let config_yaml = serde_yaml::to_string(&empty_config)
-                .expect("Failed to serialize default config");
+    let read_only_flag = args.iter().any(|a| a == "--read-only");
 
-            if let Err(e) = std::fs::write(&default_path, config_yaml) {
-                eprintln!("Error: Failed to create config file: {}", e);
+    // Load configuration, create empty one if it doesn't exist
+    let (config, actual_config_path) = if std::path::Path::new(&config_path).exists() {
+        // Never replace a config that exists but can't be read, parsed or decrypted
+        match Config::load(&config_path, || prompt_passphrase(&format!("Passphrase for {}: ", config_path))) {
+            Ok(cfg) => (cfg, config_path),
+            Err(e) => {
+                eprintln!("Error: {:#}", e);
                 std::process::exit(1);
             }
+        }
+    } else {
+        // Config doesn't exist, create it with defaults
+        eprintln!("No config found at {}", config_path);
+
+        // Determine the default config path
+        let default_path = if let Some(home_dir) = dirs::home_dir() {
+            home_dir.join(".config").join("proxmon").join("config.yml")
+        } else {
+            std::path::PathBuf::from("config.yml")
+        };
+
+        // Create the directory structure if it doesn't exist
+        if let Some(parent) = default_path.parent() {
+            if let Err(e) = std::fs::create_dir_all(parent) {
+                eprintln!("Error: Failed to create config directory: {}", e);
+                std::process::exit(1);
+            }
+        }
 
-            eprintln!("✨ Created new config at: {}", default_path.display());
-            eprintln!("📝 Tip: Press 'a' in the app to add your first Proxmox host!\n");
+        // Create an empty config with defaults
+        let empty_config = Config::default();
+        let config_yaml = serde_yaml::to_string(&empty_config)
+            .expect("Failed to serialize default config");
 
-            let path_str = default_path.to_string_lossy().to_string();
-            (empty_config, path_str)
+        if let Err(e) = config::write_private(&default_path.to_string_lossy(), &config_yaml) {
+            eprintln!("Error: Failed to create config file: {}", e);
+            std::process::exit(1);
         }
+
+        eprintln!("✨ Created new config at: {}", default_path.display());
+        eprintln!("📝 Tip: Press 'a' in the app to add your first Proxmox host!\n");
+
+        let path_str = default_path.to_string_lossy().to_string();
+        (empty_config, path_str)
     };
 
+    proxmox::set_read_only(read_only_flag || config.read_only);
+
+    // Warn before the alternate screen hides the terminal
+    let startup_warning = config::permission_warning(&actual_config_path);
+    if let Some(warning) = &startup_warning {
+        eprintln!("{}", warning);
+    }
+
     // Setup terminal
     enable_raw_mode()?;
     let mut stdout = io::stdout();
@@ -85,6 +120,7 @@ async fn main() -> Result<()> {
 
     // Create app (starts in loading state)
     let mut app = App::new(config, actual_config_path);
+    app.startup_warning = startup_warning;
 
     // Run the app (initial data fetch happens inside the loop)
     let res = run_app(&mut terminal, &mut app).await;
@@ -106,118 +142,96 @@ async fn main() -> Result<()> {
 }
 
 async fn run_app<B: Backend>(terminal: &mut Terminal<B>, app: &mut App) -> Result<()> {
-    // Perform initial fetch with animated loading screen
-    if !app.initial_fetch_done {
-        // Spawn the fetch task
-        let (tx, mut rx) = tokio::sync::mpsc::channel(1);
-        let config = app.config.clone();
-
-        tokio::spawn(async move {
-            let mut all_hosts = Vec::new();
-
-            // Fetch from each Proxmox host
-            for pve_host in &config.proxmox_hosts {
-                match crate::proxmox::ProxmoxClient::new(pve_host) {
-                    Ok(client) => {
-                        match client.fetch_all_hosts().await {
-                            Ok(hosts) => {
-                                all_hosts.extend(hosts);
-                            }
-                            Err(e) => {
-                                let _ = tx.send(Err(format!("Error fetching from {}: {}", pve_host.name, e))).await;
-                                return;
-                            }
-                        }
-                    }
-                    Err(e) => {
-                        let _ = tx.send(Err(format!("Error creating client for {}: {}", pve_host.name, e))).await;
-                        return;
-                    }
-                }
-            }
+    // Kick off the initial fetch; the loading screen is shown until it completes
+    app.start_refresh();
 
-            let _ = tx.send(Ok(all_hosts)).await;
-        });
+    loop {
+        // Pick up everything background workers reported since the last frame
+        app.process_events();
 
-        // Keep drawing loading screen until fetch completes
-        loop {
-            app.tick_loading_animation();
-            terminal.draw(|f| ui::render(f, app))?;
-
-            // Check if fetch completed
-            if let Ok(result) = rx.try_recv() {
-                match result {
-                    Ok(mut hosts) => {
-                        // Apply IP overrides
-                        for host in &mut hosts {
-                            if let Some(override_entry) = app.config.ip_overrides.iter().find(|o| o.name == host.name) {
-                                host.ip = Some(override_entry.ip.clone());
-                            }
-                        }
-
-                        // Add manual hosts
-                        for manual_host in &app.config.manual_hosts {
-                            hosts.push(crate::proxmox::Host {
-                                name: manual_host.name.clone(),
-                                host_type: crate::proxmox::HostType::Physical,
-                                status: "unknown".to_string(),
-                                ip: Some(manual_host.ip.clone()),
-                                node: None,
-                                vmid: None,
-                                ansible_user: Some(manual_host.ansible_user.clone()),
-                            });
-                        }
-
-                        app.hosts = hosts;
-                        app.apply_sort();
-
-                        if app.selected_index >= app.hosts.len() && !app.hosts.is_empty() {
-                            app.selected_index = app.hosts.len() - 1;
-                        }
-                    }
-                    Err(e) => {
-                        app.last_error = Some(e);
-                    }
-                }
+        // Drive the loading screen and refresh spinners
+        app.tick_loading_animation();
+        app.tick_auto_refresh();
 
-                app.is_loading = false;
-                app.initial_fetch_done = true;
-                break;
-            }
+        terminal.draw(|f| ui::render(f, app))?;
 
-            // Handle quit during loading
-            if event::poll(Duration::from_millis(100))? {
-                if let Event::Key(key) = event::read()? {
-                    if key.code == crossterm::event::KeyCode::Char('q')
-                        || key.code == crossterm::event::KeyCode::Char('Q')
-                        || (key.code == crossterm::event::KeyCode::Char('c')
-                            && key.modifiers.contains(crossterm::event::KeyModifiers::CONTROL)) {
-                        app.should_quit = true;
-                        return Ok(());
-                    }
-                }
+        // Handle input with a short timeout so background results show up promptly
+        if event::poll(Duration::from_millis(100))? {
+            if let Event::Key(key) = event::read()? {
+                app.handle_key_event(key);
             }
         }
-    }
 
-    loop {
-        terminal.draw(|f| ui::render(f, app))?;
+        if app.should_quit {
+            return Ok(());
+        }
+    }
+}
 
-        // Handle events with a timeout
-        if event::poll(Duration::from_millis(250))? {
-            if let Event::Key(key) = event::read()? {
-                app.handle_key_event(key);
+/// Read a passphrase from the terminal without echoing it
+fn prompt_passphrase(prompt: &str) -> Result<SecretString> {
+    eprint!("{}", prompt);
+    io::stderr().flush()?;
 
-                // Check if we need to refresh data
-                if key.code == crossterm::event::KeyCode::Char('r')
-                    || key.code == crossterm::event::KeyCode::Char('R') {
-                    app.fetch_all_hosts().await?;
+    enable_raw_mode()?;
+    let mut passphrase = String::new();
+    let result = loop {
+        match event::read() {
+            Ok(Event::Key(key)) if key.kind == KeyEventKind::Press => match key.code {
+                KeyCode::Enter => break Ok(()),
+                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    break Err(anyhow::anyhow!("Cancelled"))
                 }
+                KeyCode::Backspace => {
+                    passphrase.pop();
+                }
+                KeyCode::Char(c) => passphrase.push(c),
+                _ => {}
+            },
+            Ok(_) => {}
+            Err(e) => break Err(e.into()),
+        }
+    };
+    disable_raw_mode()?;
+    eprintln!();
+
+    result.map(|_| SecretString::from(passphrase))
+}
+
+/// `proxmon config encrypt|decrypt`: convert the config file in place
+fn config_command(path: &str, action: Option<&str>) -> Result<()> {
+    let raw = std::fs::read(path).with_context(|| format!("Failed to read config file: {}", path))?;
+
+    match action {
+        Some("encrypt") => {
+            if config::is_encrypted(&raw) {
+                anyhow::bail!("{} is already encrypted", path);
+            }
+            // The file is encrypted as-is so comments survive, but only if it's a valid config
+            let contents = String::from_utf8(raw).context("Config file is not valid UTF-8")?;
+            serde_yaml::from_str::<Config>(&contents).context("Failed to parse config file")?;
+
+            let passphrase = prompt_passphrase("New passphrase: ")?;
+            if passphrase.expose_secret().is_empty() {
+                anyhow::bail!("Passphrase can't be empty");
             }
+            if prompt_passphrase("Repeat passphrase: ")?.expose_secret() != passphrase.expose_secret() {
+                anyhow::bail!("Passphrases don't match");
+            }
+
+            config::write_private(path, &config::encrypt(&contents, &passphrase)?)?;
+            eprintln!("Encrypted {}", path);
         }
+        Some("decrypt") => {
+            if !config::is_encrypted(&raw) {
+                anyhow::bail!("{} is not encrypted", path);
+            }
+            let passphrase = prompt_passphrase(&format!("Passphrase for {}: ", path))?;
 
-        if app.should_quit {
-            return Ok(());
+            config::write_private(path, &config::decrypt(&raw, &passphrase)?)?;
+            eprintln!("Decrypted {}", path);
         }
+        _ => anyhow::bail!("Usage: proxmon config encrypt|decrypt"),
     }
+    Ok(())
 }
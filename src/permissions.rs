@@ -0,0 +1,88 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+
+// Privileges proxmon checks before doing something
+pub const VM_POWER_MGMT: &str = "VM.PowerMgmt";
+pub const VM_SNAPSHOT: &str = "VM.Snapshot";
+pub const VM_SNAPSHOT_ROLLBACK: &str = "VM.Snapshot.Rollback";
+pub const VM_BACKUP: &str = "VM.Backup";
+pub const VM_ALLOCATE: &str = "VM.Allocate";
+pub const VM_MIGRATE: &str = "VM.Migrate";
+pub const VM_CLONE: &str = "VM.Clone";
+// Reading guest agent data needs VM.Monitor before PVE 9 and VM.GuestAgent.Audit since
+const GUEST_AGENT_PRIVS: [&str; 2] = ["VM.Monitor", "VM.GuestAgent.Audit"];
+
+/// Effective privileges of the credentials a cluster is queried with, as
+/// returned by /access/permissions: path -> privilege -> propagate flag
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(transparent)]
+pub struct Permissions {
+    paths: HashMap<String, HashMap<String, u8>>,
+}
+
+impl Permissions {
+    /// Whether `privilege` is granted on `path`, taken from the most specific
+    /// listed path above it. Privileges of a parent path only count when they
+    /// propagate.
+    fn has(&self, path: &str, privilege: &str) -> bool {
+        let mut current = path;
+        loop {
+            if let Some(privs) = self.paths.get(current) {
+                return match privs.get(privilege) {
+                    Some(_) if current == path => true,
+                    Some(propagate) => *propagate == 1,
+                    None => false,
+                };
+            }
+            current = match current.rsplit_once('/') {
+                Some(("", _)) if current != "/" => "/",
+                Some((parent, _)) if !parent.is_empty() => parent,
+                _ => return false,
+            };
+        }
+    }
+
+    /// Whether `privilege` is granted on a guest, directly or through its pool
+    pub fn guest_has(&self, vmid: u32, pool: Option<&str>, privilege: &str) -> bool {
+        self.has(&format!("/vms/{}", vmid), privilege)
+            || pool.is_some_and(|pool| self.has(&format!("/pool/{}", pool), privilege))
+    }
+
+    /// Whether `privilege` is granted for guests that don't exist yet (on /vms)
+    pub fn new_guest_has(&self, privilege: &str) -> bool {
+        self.has("/vms", privilege)
+    }
+
+    /// Whether IPs can be read through the QEMU guest agent
+    pub fn can_read_guest_agent(&self, vmid: u32, pool: Option<&str>) -> bool {
+        GUEST_AGENT_PRIVS.iter().any(|p| self.guest_has(vmid, pool, p))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_guest_privileges_from_most_specific_path() {
+        let permissions: Permissions = serde_json::from_str(
+            r#"{
+                "/": {"Sys.Audit": 1, "VM.Audit": 1, "VM.Backup": 0},
+                "/vms/101": {"VM.Audit": 1, "VM.GuestAgent.Audit": 0, "VM.PowerMgmt": 0},
+                "/pool/lab": {"VM.Audit": 1, "VM.Monitor": 1}
+            }"#,
+        )
+        .unwrap();
+
+        assert!(permissions.guest_has(100, None, "VM.Audit"));
+        assert!(!permissions.guest_has(100, None, VM_POWER_MGMT));
+        assert!(permissions.guest_has(101, None, VM_POWER_MGMT));
+        // Granted on / without propagate: not on the guests below it
+        assert!(!permissions.guest_has(100, None, VM_BACKUP));
+        assert!(!permissions.new_guest_has(VM_ALLOCATE));
+
+        assert!(!permissions.can_read_guest_agent(100, None));
+        assert!(permissions.can_read_guest_agent(101, None));
+        assert!(permissions.can_read_guest_agent(100, Some("lab")));
+    }
+}
@@ -1,8 +1,18 @@
 use anyhow::{Context, Result};
 use serde::Deserialize;
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Semaphore;
 
-use crate::config::ProxmoxHost;
+use crate::auth::{Credential, LoginStep, NotLoggedIn, SessionStore, TicketResponse};
+use crate::backups::{self, Backup, BackupMode, Compression};
+use crate::containers::{ContainerSpec, NodeOptions};
+use crate::config::{AuthMode, ManualHost, ProxmoxHost};
+use crate::permissions::{self, Permissions};
+use crate::snapshots::{self, CountCache, Snapshot};
+use crate::tls;
 
 #[derive(Debug, Clone)]
 pub struct Host {
@@ -10,13 +20,78 @@ pub struct Host {
     pub host_type: HostType,
     pub status: String,
     pub ip: Option<String>,
+    // Why the IP couldn't be looked up, shown instead of N/A
+    pub ip_unavailable: Option<String>,
+    // Number of snapshots, None if they couldn't be listed
+    pub snapshots: Option<usize>,
     pub node: Option<String>,
-    #[allow(dead_code)] // Kept for future features (start/stop VMs)
     pub vmid: Option<u32>,
     pub ansible_user: Option<String>,
+    // Name of the configured Proxmox host this guest was fetched from
+    pub cluster: Option<String>,
+    pub pool: Option<String>,
+    pub template: bool,
+    pub tags: Vec<String>,
+    // Resource usage as reported by /cluster/resources
+    pub cpu: Option<f64>,
+    pub mem: Option<u64>,
+    pub maxmem: Option<u64>,
+    pub uptime: Option<u64>,
+}
+
+/// Stable identity of a host that survives refreshes and re-sorting: cluster
+/// plus vmid for Proxmox guests, the configured name for manual hosts
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum HostId {
+    Guest { cluster: String, vmid: u32 },
+    Manual(String),
+}
+
+impl std::fmt::Display for HostId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HostId::Guest { cluster, vmid } => write!(f, "{}/{}", cluster, vmid),
+            HostId::Manual(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+impl Host {
+    pub fn id(&self) -> HostId {
+        match (&self.cluster, self.vmid) {
+            (Some(cluster), Some(vmid)) => HostId::Guest {
+                cluster: cluster.clone(),
+                vmid,
+            },
+            _ => HostId::Manual(self.name.clone()),
+        }
+    }
+
+    pub fn from_manual(manual_host: &ManualHost) -> Self {
+        Self {
+            name: manual_host.name.clone(),
+            host_type: HostType::Physical,
+            status: "unknown".to_string(),
+            ip: Some(manual_host.ip.clone()),
+            ip_unavailable: None,
+            snapshots: None,
+            node: None,
+            vmid: None,
+            ansible_user: Some(manual_host.ansible_user.clone()),
+            cluster: None,
+            pool: None,
+            template: false,
+            tags: Vec::new(),
+            cpu: None,
+            mem: None,
+            maxmem: None,
+            uptime: None,
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[allow(clippy::upper_case_acronyms)]
 pub enum HostType {
     VM,
     LXC,
@@ -31,6 +106,243 @@ impl HostType {
             HostType::Physical => "Physical",
         }
     }
+
+    /// Path segment used by the Proxmox API for this guest type
+    pub fn api_path(&self) -> Option<&str> {
+        match self {
+            HostType::VM => Some("qemu"),
+            HostType::LXC => Some("lxc"),
+            HostType::Physical => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PowerAction {
+    Start,
+    Shutdown,
+    Stop,
+    Reboot,
+    Suspend,
+    Resume,
+}
+
+impl PowerAction {
+    /// Action name as used in `/status/{action}`
+    pub fn as_str(&self) -> &str {
+        match self {
+            PowerAction::Start => "start",
+            PowerAction::Shutdown => "shutdown",
+            PowerAction::Stop => "stop",
+            PowerAction::Reboot => "reboot",
+            PowerAction::Suspend => "suspend",
+            PowerAction::Resume => "resume",
+        }
+    }
+
+    pub fn label(&self) -> &str {
+        match self {
+            PowerAction::Start => "Start",
+            PowerAction::Shutdown => "Shutdown",
+            PowerAction::Stop => "Stop (hard)",
+            PowerAction::Reboot => "Reboot",
+            PowerAction::Suspend => "Suspend",
+            PowerAction::Resume => "Resume",
+        }
+    }
+
+    /// Actions that take a guest down, which protected rules guard
+    pub fn is_destructive(&self) -> bool {
+        !matches!(self, PowerAction::Start | PowerAction::Resume)
+    }
+}
+
+/// Settings of a clone made from the clone form
+#[derive(Debug, Clone, PartialEq)]
+pub struct CloneSpec {
+    pub vmid: u32,
+    // Empty lets Proxmox pick a name
+    pub name: String,
+    pub target: String,
+    // Full copy instead of a linked clone (which only templates support)
+    pub full: bool,
+    // Target storage of a full clone, None keeps the source's storage
+    pub storage: Option<String>,
+    pub pool: Option<String>,
+    // Cloud-init settings applied before the first start (VMs only)
+    pub ciuser: Option<String>,
+    pub sshkeys: Option<String>,
+    pub ipconfig0: Option<String>,
+    pub start: bool,
+}
+
+/// Something proxmon does to a guest on request, run as a Proxmox task
+#[derive(Debug, Clone, PartialEq)]
+pub enum GuestAction {
+    Power(PowerAction),
+    CreateSnapshot {
+        name: String,
+        description: String,
+        // Save the VM's RAM too
+        vmstate: bool,
+    },
+    RollbackSnapshot(String),
+    DeleteSnapshot(String),
+    Backup {
+        storage: String,
+        mode: BackupMode,
+        compress: Compression,
+    },
+    // Restore a backup archive of the guest as a new guest
+    Restore {
+        archive: String,
+        vmid: u32,
+    },
+    // Move the guest to another node; running guests are migrated live (VMs)
+    // or restarted on the target (containers)
+    Migrate {
+        target: String,
+        running: bool,
+    },
+    Clone(Box<CloneSpec>),
+}
+
+impl GuestAction {
+    pub fn label(&self) -> String {
+        match self {
+            GuestAction::Power(action) => action.label().to_string(),
+            GuestAction::CreateSnapshot { name, vmstate: true, .. } => format!("Snapshot '{}' (with RAM)", name),
+            GuestAction::CreateSnapshot { name, .. } => format!("Snapshot '{}'", name),
+            GuestAction::RollbackSnapshot(name) => format!("Rollback to '{}'", name),
+            GuestAction::DeleteSnapshot(name) => format!("Delete snapshot '{}'", name),
+            GuestAction::Backup { storage, mode, compress } => {
+                format!("Backup to {} ({}, {})", storage, mode.as_str(), compress.label())
+            }
+            GuestAction::Restore { vmid, .. } => format!("Restore as {}", vmid),
+            GuestAction::Migrate { target, .. } => format!("Migrate to {}", target),
+            GuestAction::Clone(spec) if spec.full => format!("Full clone as {}", spec.vmid),
+            GuestAction::Clone(spec) => format!("Linked clone as {}", spec.vmid),
+        }
+    }
+
+    /// Actions that lose state, which protected rules guard
+    pub fn is_destructive(&self) -> bool {
+        match self {
+            GuestAction::Power(action) => action.is_destructive(),
+            GuestAction::CreateSnapshot { .. }
+            | GuestAction::Backup { .. }
+            | GuestAction::Restore { .. }
+            | GuestAction::Migrate { .. }
+            | GuestAction::Clone(_) => false,
+            GuestAction::RollbackSnapshot(_) | GuestAction::DeleteSnapshot(_) => true,
+        }
+    }
+
+    /// Privilege the action needs on the guest
+    pub fn privilege(&self) -> &'static str {
+        match self {
+            GuestAction::Power(_) => permissions::VM_POWER_MGMT,
+            GuestAction::CreateSnapshot { .. } | GuestAction::DeleteSnapshot(_) => permissions::VM_SNAPSHOT,
+            GuestAction::RollbackSnapshot(_) => permissions::VM_SNAPSHOT_ROLLBACK,
+            GuestAction::Backup { .. } => permissions::VM_BACKUP,
+            // Checked on the source guest, as the new vmid has no ACL of its own yet
+            GuestAction::Restore { .. } => permissions::VM_ALLOCATE,
+            GuestAction::Migrate { .. } => permissions::VM_MIGRATE,
+            GuestAction::Clone(_) => permissions::VM_CLONE,
+        }
+    }
+
+    /// Node the guest is on once the action finished
+    pub fn node_after<'a>(&'a self, node: &'a str) -> &'a str {
+        match self {
+            GuestAction::Migrate { target, .. } => target,
+            _ => node,
+        }
+    }
+
+    pub fn is_snapshot(&self) -> bool {
+        matches!(
+            self,
+            GuestAction::CreateSnapshot { .. } | GuestAction::RollbackSnapshot(_) | GuestAction::DeleteSnapshot(_)
+        )
+    }
+}
+
+/// Non-success HTTP response from the Proxmox API
+#[derive(Debug)]
+pub struct HttpError {
+    pub status: reqwest::StatusCode,
+    pub body: String,
+}
+
+impl std::fmt::Display for HttpError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "API request failed with status {}: {}", self.status, self.body)
+    }
+}
+
+impl std::error::Error for HttpError {}
+
+/// Health of a configured cluster after the last refresh
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ClusterStatus {
+    Ok,
+    AuthFailed,
+    Unreachable,
+    TlsError,
+    // The certificate no longer matches the pinned fingerprint
+    CertificateChanged,
+    Error,
+}
+
+impl ClusterStatus {
+    pub fn as_str(&self) -> &str {
+        match self {
+            ClusterStatus::Ok => "ok",
+            ClusterStatus::AuthFailed => "auth failed",
+            ClusterStatus::Unreachable => "unreachable",
+            ClusterStatus::TlsError => "TLS error",
+            ClusterStatus::CertificateChanged => "CERTIFICATE CHANGED",
+            ClusterStatus::Error => "error",
+        }
+    }
+
+    /// Classify a failed request by walking its error chain
+    pub fn from_error(error: &anyhow::Error) -> Self {
+        for cause in error.chain() {
+            if cause.downcast_ref::<NotLoggedIn>().is_some() {
+                return ClusterStatus::AuthFailed;
+            }
+
+            if let Some(http) = cause.downcast_ref::<HttpError>() {
+                return match http.status.as_u16() {
+                    401 | 403 => ClusterStatus::AuthFailed,
+                    _ => ClusterStatus::Error,
+                };
+            }
+
+            if let Some(req) = cause.downcast_ref::<reqwest::Error>() {
+                // TLS failures surface as connect errors, so look for them first
+                let mut source = std::error::Error::source(req);
+                while let Some(inner) = source {
+                    let text = inner.to_string().to_lowercase();
+                    if text.contains("fingerprint mismatch") {
+                        return ClusterStatus::CertificateChanged;
+                    }
+                    if text.contains("certificate") || text.contains("tls") || text.contains("handshake") {
+                        return ClusterStatus::TlsError;
+                    }
+                    source = inner.source();
+                }
+
+                if req.is_connect() || req.is_timeout() {
+                    return ClusterStatus::Unreachable;
+                }
+            }
+        }
+
+        ClusterStatus::Error
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -39,15 +351,110 @@ struct ProxmoxResponse<T> {
 }
 
 #[derive(Debug, Deserialize)]
-struct Node {
-    node: String,
+struct ClusterResource {
+    #[serde(rename = "type")]
+    resource_type: String,
+    vmid: Option<u32>,
+    name: Option<String>,
+    node: Option<String>,
+    status: Option<String>,
+    pool: Option<String>,
+    #[serde(default)]
+    template: u8,
+    tags: Option<String>,
+    cpu: Option<f64>,
+    mem: Option<u64>,
+    maxmem: Option<u64>,
+    uptime: Option<u64>,
 }
 
 #[derive(Debug, Deserialize)]
-struct VmInfo {
-    vmid: u32,
+struct ClusterStatusEntry {
+    #[serde(rename = "type")]
+    entry_type: String,
     name: String,
+    // Only set on node entries
+    ip: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct VersionInfo {
+    version: String,
+}
+
+/// One cluster node from /nodes
+#[derive(Debug, Clone, Deserialize)]
+pub struct NodeInfo {
+    pub node: String,
+    pub status: Option<String>,
+    pub mem: Option<u64>,
+    pub maxmem: Option<u64>,
+}
+
+impl NodeInfo {
+    pub fn is_online(&self) -> bool {
+        self.status.as_deref() == Some("online")
+    }
+
+    /// Memory not used by the node, if it reports its usage
+    pub fn free_mem(&self) -> Option<u64> {
+        Some(self.maxmem?.saturating_sub(self.mem?))
+    }
+}
+
+/// A storage of a node, from /nodes/{node}/storage
+#[derive(Debug, Clone, Deserialize)]
+pub struct StorageInfo {
+    pub storage: String,
+    // Bytes still free; missing when the storage is unavailable
+    pub avail: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct StorageContent {
+    volid: String,
+}
+
+/// Result of a connection check run before a new host is saved
+#[derive(Debug, Clone)]
+pub struct ConnectionReport {
+    pub version: String,
+    pub nodes: usize,
+    // Things that work but look wrong, e.g. missing permissions
+    pub warnings: Vec<String>,
+}
+
+/// A freshly created API token; the secret is only ever returned once
+#[derive(Debug, Deserialize)]
+pub struct NewToken {
+    #[serde(rename = "full-tokenid")]
+    pub full_tokenid: String,
+    pub value: String,
+}
+
+/// What /cluster/status says about the cluster behind an endpoint
+pub struct ClusterInfo {
+    // None for a standalone node
+    pub name: Option<String>,
+    // (node name, address) of every member node
+    pub nodes: Vec<(String, String)>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TaskStatus {
+    pub status: String,
+    pub exitstatus: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TaskLogLine {
+    t: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct GuestStatus {
     status: String,
+    qmpstatus: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -56,44 +463,412 @@ struct NetworkInterface {
     ip_address: Option<String>,
 }
 
-pub struct ProxmoxClient {
-    client: reqwest::Client,
+// Set at startup from --read-only or `read_only: true`; every client then refuses
+// requests that would change something
+static READ_ONLY: AtomicBool = AtomicBool::new(false);
+
+pub fn set_read_only(read_only: bool) {
+    READ_ONLY.store(read_only, Ordering::Relaxed);
+}
+
+pub fn is_read_only() -> bool {
+    READ_ONLY.load(Ordering::Relaxed)
+}
+
+/// How requests to an endpoint are authenticated
+#[derive(Clone)]
+enum Auth {
+    // "user@realm!tokenid=secret"
+    Token(String),
+    // Ticket looked up by config entry name in the shared session store
+    Ticket {
+        entry: String,
+        username: String,
+        sessions: SessionStore,
+    },
+}
+
+impl Auth {
+    fn apply(&self, request: reqwest::RequestBuilder, method: &reqwest::Method) -> Result<reqwest::RequestBuilder> {
+        match self {
+            Auth::Token(token) => Ok(request.header("Authorization", format!("PVEAPIToken={}", token))),
+            Auth::Ticket { entry, sessions, .. } => {
+                let ticket = sessions
+                    .get(entry)
+                    .filter(|t| t.is_valid())
+                    .ok_or(NotLoggedIn)?;
+
+                let request = request.header("Cookie", format!("PVEAuthCookie={}", ticket.ticket));
+                // Proxmox rejects ticket-authenticated writes without the CSRF token
+                if *method == reqwest::Method::GET {
+                    Ok(request)
+                } else {
+                    Ok(request.header("CSRFPreventionToken", ticket.csrf_token))
+                }
+            }
+        }
+    }
+}
+
+/// One address a cluster can be reached at
+#[derive(Clone)]
+struct Endpoint {
+    label: String,
+    http: reqwest::Client,
     base_url: String,
-    token: String,
+    auth: Auth,
+    // Separate credentials for mutating requests, if configured
+    operator: Option<Auth>,
 }
 
-impl ProxmoxClient {
-    pub fn new(config: &ProxmoxHost) -> Result<Self> {
-        let client = reqwest::Client::builder()
-            .danger_accept_invalid_certs(!config.verify_ssl)
+impl Endpoint {
+    fn auth(&self, credential: Credential) -> &Auth {
+        match (credential, &self.operator) {
+            (Credential::Operator, Some(operator)) => operator,
+            _ => &self.auth,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct ProxmoxClient {
+    name: String,
+    // Tried in order starting from the last one that answered
+    endpoints: Vec<Endpoint>,
+    active: Arc<AtomicUsize>,
+    // Bounds the number of concurrent requests against this cluster
+    limiter: Arc<Semaphore>,
+    // Set while a ticket renewal is in flight so concurrent requests don't all renew
+    renewing: Arc<AtomicBool>,
+    // HTTP client for discovered nodes, which never match a pinned certificate
+    unpinned_http: reqwest::Client,
+}
+
+fn base_url(host: &str, port: u16) -> String {
+    if host.contains(':') {
+        format!("https://[{}]:{}/api2/json", host, port)
+    } else {
+        format!("https://{}:{}/api2/json", host, port)
+    }
+}
+
+/// The pinned fingerprint that applies to one address of a config entry. A pin is
+/// taken from `host` and each node has its own certificate, so the other
+/// addresses are verified against `ca_file` (or the system roots) instead.
+fn endpoint_pin<'a>(config: &'a ProxmoxHost, host: &str, port: u16) -> Option<&'a str> {
+    config
+        .fingerprint
+        .as_deref()
+        .filter(|_| config.host == host && config.port == port)
+}
+
+/// HTTP client with the TLS, proxy and timeout settings of one config entry,
+/// trusting only the certificate with fingerprint `pin` if given
+fn http_client(config: &ProxmoxHost, pin: Option<&str>) -> Result<reqwest::Client> {
+    // Short connect timeouts keep a node that is down from stalling a refresh
+    // before failing over; the request timeout bounds everything else
+    let mut builder = reqwest::Client::builder()
+        .connect_timeout(Duration::from_secs(config.connect_timeout.max(1)))
+        .timeout(Duration::from_secs(config.request_timeout.max(1)));
+
+    if let Some(proxy) = &config.proxy {
+        let proxy = reqwest::Proxy::all(proxy).with_context(|| format!("Invalid proxy URL '{}'", proxy))?;
+        builder = builder.proxy(proxy);
+    }
+
+    // A pinned certificate is trusted on its own, whoever signed it
+    if let Some(pin) = pin {
+        return builder
+            .use_preconfigured_tls(tls::pinned_config(pin)?)
             .build()
-            .context("Failed to create HTTP client")?;
+            .context("Failed to create HTTP client");
+    }
+
+    if let Some(ca_file) = &config.ca_file {
+        let pem = std::fs::read(ca_file).with_context(|| format!("Failed to read CA file {}", ca_file))?;
+        let certs = reqwest::Certificate::from_pem_bundle(&pem)
+            .with_context(|| format!("Failed to parse CA file {}", ca_file))?;
+        for cert in certs {
+            builder = builder.add_root_certificate(cert);
+        }
+    }
+
+    // A CA file means the certificate is meant to be verified against it, and an
+    // entry with a pin never falls back to accepting any certificate
+    let accept_any = !config.verify_ssl && config.ca_file.is_none() && config.fingerprint.is_none();
+    builder
+        .danger_accept_invalid_certs(accept_any)
+        .build()
+        .context("Failed to create HTTP client")
+}
+
+impl ProxmoxClient {
+    pub fn new(config: &ProxmoxHost, sessions: &SessionStore) -> Result<Self> {
+        let unpinned_http = http_client(config, None)?;
+        let pinned_http = match &config.fingerprint {
+            Some(pin) => http_client(config, Some(pin))?,
+            None => unpinned_http.clone(),
+        };
 
-        let base_url = format!("https://{}:{}/api2/json", config.host, config.port);
-        let token = format!("{}={}", config.api_token_id, config.api_token_secret);
+        let auth = match config.auth {
+            AuthMode::Token => Auth::Token(format!("{}={}", config.api_token_id, config.token_secret()?)),
+            AuthMode::Ticket => Auth::Ticket {
+                entry: Credential::Viewer.session_key(&config.name),
+                username: config.user_at_realm(),
+                sessions: sessions.clone(),
+            },
+        };
+        let operator = if !config.operator_token_id.is_empty() {
+            Some(Auth::Token(format!("{}={}", config.operator_token_id, config.operator_token_secret()?)))
+        } else if !config.operator_username.is_empty() {
+            Some(Auth::Ticket {
+                entry: Credential::Operator.session_key(&config.name),
+                username: config.operator_user_at_realm(),
+                sessions: sessions.clone(),
+            })
+        } else {
+            None
+        };
+        let endpoints = config
+            .addresses()
+            .into_iter()
+            .map(|(host, port)| Endpoint {
+                label: format!("{} ({}:{})", config.name, host, port),
+                http: match endpoint_pin(config, &host, port) {
+                    Some(_) => pinned_http.clone(),
+                    None => unpinned_http.clone(),
+                },
+                base_url: base_url(&host, port),
+                auth: auth.clone(),
+                operator: operator.clone(),
+            })
+            .collect();
 
         Ok(Self {
-            client,
-            base_url,
-            token,
+            name: config.name.clone(),
+            endpoints,
+            active: Arc::new(AtomicUsize::new(0)),
+            limiter: Arc::new(Semaphore::new(config.max_concurrency.max(1))),
+            renewing: Arc::new(AtomicBool::new(false)),
+            unpinned_http,
         })
     }
 
+    fn add_endpoint(&mut self, endpoint: Endpoint) {
+        if !self.endpoints.iter().any(|e| e.base_url == endpoint.base_url) {
+            self.endpoints.push(endpoint);
+        }
+    }
+
+    /// Add another client's endpoints as failover targets for this one
+    pub fn merge(&mut self, other: ProxmoxClient) {
+        for endpoint in other.endpoints {
+            self.add_endpoint(endpoint);
+        }
+    }
+
+    /// Add node addresses found via /cluster/status as failover targets, reusing
+    /// the credentials and TLS settings (except a pin) of the configured endpoint
+    pub fn add_discovered(&mut self, config: &ProxmoxHost, nodes: &[(String, String)]) {
+        let template = self.endpoints[0].clone();
+        let port = config.port;
+
+        for (node, ip) in nodes {
+            self.add_endpoint(Endpoint {
+                label: format!("{} ({}:{})", node, ip, port),
+                base_url: base_url(ip, port),
+                http: self.unpinned_http.clone(),
+                ..template.clone()
+            });
+        }
+    }
+
+    /// Start with the first endpoint whose label is in `labels`, e.g. the ones
+    /// that answered during the previous refresh
+    pub fn prefer(&self, labels: &[String]) {
+        if let Some(index) = self.endpoints.iter().position(|e| labels.contains(&e.label)) {
+            self.active.store(index, Ordering::Relaxed);
+        }
+    }
+
+    fn active_index(&self) -> usize {
+        self.active.load(Ordering::Relaxed).min(self.endpoints.len() - 1)
+    }
+
+    /// Label of the endpoint that answered most recently
+    pub fn active_endpoint(&self) -> &str {
+        &self.endpoints[self.active_index()].label
+    }
+
+    /// True when mutating requests use separate operator credentials
+    pub fn has_operator(&self) -> bool {
+        self.endpoints.iter().any(|e| e.operator.is_some())
+    }
+
+    /// True when the data is served by a failover endpoint rather than the primary one
+    pub fn is_failed_over(&self) -> bool {
+        self.active_index() != 0
+    }
+
     async fn get<T: for<'de> Deserialize<'de>>(&self, path: &str) -> Result<T> {
-        let url = format!("{}{}", self.base_url, path);
+        self.send(reqwest::Method::GET, path, None).await
+    }
 
-        let response = self
-            .client
-            .get(&url)
-            .header("Authorization", format!("PVEAPIToken={}", self.token))
-            .send()
-            .await
-            .context("Failed to send request")?;
+    async fn post<T: for<'de> Deserialize<'de>>(&self, path: &str, params: &[(&str, String)]) -> Result<T> {
+        self.send(reqwest::Method::POST, path, Some(params)).await
+    }
+
+    async fn delete<T: for<'de> Deserialize<'de>>(&self, path: &str) -> Result<T> {
+        self.send(reqwest::Method::DELETE, path, None).await
+    }
+
+    async fn put<T: for<'de> Deserialize<'de>>(&self, path: &str, params: &[(&str, String)]) -> Result<T> {
+        self.send(reqwest::Method::PUT, path, Some(params)).await
+    }
+
+    async fn send<T: for<'de> Deserialize<'de>>(
+        &self,
+        method: reqwest::Method,
+        path: &str,
+        params: Option<&[(&str, String)]>,
+    ) -> Result<T> {
+        // Only reads go out with the viewer credentials
+        let credential = if method == reqwest::Method::GET {
+            Credential::Viewer
+        } else {
+            Credential::Operator
+        };
+        if credential == Credential::Operator && is_read_only() {
+            anyhow::bail!("Read-only mode: refusing {} {}", method, path);
+        }
+        self.renew_ticket(credential).await;
+        self.send_with(method, path, params, Some(credential)).await
+    }
 
+    /// Send a request, authenticated with `credential` unless it is None
+    async fn send_with<T: for<'de> Deserialize<'de>>(
+        &self,
+        method: reqwest::Method,
+        path: &str,
+        params: Option<&[(&str, String)]>,
+        credential: Option<Credential>,
+    ) -> Result<T> {
+        let _permit = self.limiter.acquire().await.context("Request limiter closed")?;
+
+        let start = self.active.load(Ordering::Relaxed);
+        let mut last_error = None;
+
+        for offset in 0..self.endpoints.len() {
+            let index = (start + offset) % self.endpoints.len();
+            let endpoint = &self.endpoints[index];
+
+            let mut request = endpoint
+                .http
+                .request(method.clone(), format!("{}{}", endpoint.base_url, path));
+            if let Some(credential) = credential {
+                request = endpoint.auth(credential).apply(request, &method)?;
+            }
+            if let Some(params) = params {
+                request = request.form(params);
+            }
+
+            match request.send().await {
+                Ok(response) => {
+                    self.active.store(index, Ordering::Relaxed);
+                    return Self::parse_response(response).await;
+                }
+                // Fail over only when the endpoint itself is unreachable. A timed out
+                // POST may still have been applied, so those are never retried.
+                Err(e) if e.is_connect() || (e.is_timeout() && method == reqwest::Method::GET) => {
+                    last_error = Some(e);
+                }
+                Err(e) => return Err(anyhow::Error::new(e).context("Failed to send request")),
+            }
+        }
+
+        match last_error {
+            Some(e) => Err(anyhow::Error::new(e).context("Failed to send request")),
+            None => anyhow::bail!("No endpoints configured"),
+        }
+    }
+
+    /// Ticket-auth user behind `credential`, if it uses ticket auth
+    fn ticket_user(&self, credential: Credential) -> Option<(&str, &str, &SessionStore)> {
+        self.endpoints.iter().find_map(|e| match e.auth(credential) {
+            Auth::Ticket { entry, username, sessions } => Some((entry.as_str(), username.as_str(), sessions)),
+            Auth::Token(_) => None,
+        })
+    }
+
+    /// False when `credential` is a ticket login without a valid ticket
+    pub fn is_logged_in(&self, credential: Credential) -> bool {
+        self.ticket_user(credential)
+            .is_none_or(|(entry, _, sessions)| sessions.is_logged_in(entry))
+    }
+
+    async fn request_ticket(&self, credential: Credential, params: &[(&str, String)]) -> Result<LoginStep> {
+        let response: TicketResponse = self
+            .send_with(reqwest::Method::POST, "/access/ticket", Some(params), None)
+            .await?;
+        let step = response.into_step();
+
+        if let (LoginStep::Done(ticket), Some((entry, _, sessions))) = (&step, self.ticket_user(credential)) {
+            sessions.set(entry, ticket.clone());
+        }
+        Ok(step)
+    }
+
+    /// Log in with a password. The ticket is stored in the session store; the
+    /// password is not kept anywhere.
+    pub async fn login(&self, credential: Credential, password: &str) -> Result<LoginStep> {
+        let Some((_, username, _)) = self.ticket_user(credential) else {
+            anyhow::bail!("{} does not use ticket auth", self.name);
+        };
+        let params = [("username", username.to_string()), ("password", password.to_string())];
+        self.request_ticket(credential, &params).await.context("Login failed")
+    }
+
+    /// Finish a login that asked for a second factor
+    pub async fn login_totp(&self, credential: Credential, challenge: &str, code: &str) -> Result<LoginStep> {
+        let Some((_, username, _)) = self.ticket_user(credential) else {
+            anyhow::bail!("{} does not use ticket auth", self.name);
+        };
+        let params = [
+            ("username", username.to_string()),
+            ("tfa-challenge", challenge.to_string()),
+            ("password", format!("totp:{}", code.trim())),
+        ];
+        self.request_ticket(credential, &params).await.context("TOTP verification failed")
+    }
+
+    /// Swap an ageing ticket for a fresh one. Failures are ignored; the old ticket
+    /// stays usable until it expires and the user is asked to log in again. An
+    /// operator ticket is only renewed while it is in use, so it lapses when idle.
+    async fn renew_ticket(&self, credential: Credential) {
+        let Some((entry, _, sessions)) = self.ticket_user(credential) else {
+            return;
+        };
+        let Some(ticket) = sessions.get(entry) else {
+            return;
+        };
+        if !ticket.is_valid() || !ticket.needs_renewal() {
+            return;
+        }
+        if self.renewing.swap(true, Ordering::AcqRel) {
+            return;
+        }
+
+        // An existing ticket is accepted in place of the password
+        let params = [("username", ticket.username), ("password", ticket.ticket)];
+        let _ = self.request_ticket(credential, &params).await;
+        self.renewing.store(false, Ordering::Release);
+    }
+
+    async fn parse_response<T: for<'de> Deserialize<'de>>(response: reqwest::Response) -> Result<T> {
         if !response.status().is_success() {
             let status = response.status();
-            let text = response.text().await.unwrap_or_default();
-            anyhow::bail!("API request failed with status {}: {}", status, text);
+            let body = response.text().await.unwrap_or_default();
+            return Err(HttpError { status, body }.into());
         }
 
         let data: ProxmoxResponse<T> = response
@@ -104,66 +879,156 @@ impl ProxmoxClient {
         Ok(data.data)
     }
 
-    pub async fn list_nodes(&self) -> Result<Vec<String>> {
-        let nodes: Vec<Node> = self.get("/nodes").await?;
-        Ok(nodes.into_iter().map(|n| n.node).collect())
+    /// Create an API token `user@realm!name`
+    pub async fn create_api_token(&self, userid: &str, name: &str, privsep: bool) -> Result<NewToken> {
+        let path = format!("/access/users/{}/token/{}", userid, name);
+        let params = [
+            ("privsep", if privsep { "1" } else { "0" }.to_string()),
+            ("comment", "Created by proxmon".to_string()),
+        ];
+        self.post(&path, &params)
+            .await
+            .with_context(|| format!("Failed to create API token {}!{}", userid, name))
     }
 
-    pub async fn list_vms(&self, node: &str) -> Result<Vec<Host>> {
-        let vms: Vec<VmInfo> = self
-            .get(&format!("/nodes/{}/qemu", node))
-            .await
-            .unwrap_or_default();
-
-        let mut hosts = Vec::new();
-        for vm in vms {
-            // Try to get IP address from the VM's network configuration
-            let ip = self.get_vm_ip(node, vm.vmid).await;
-
-            hosts.push(Host {
-                name: vm.name,
-                host_type: HostType::VM,
-                status: vm.status,
-                ip,
-                node: Some(node.to_string()),
-                vmid: Some(vm.vmid),
-                ansible_user: Some("gozy".to_string()), // Default user
-            });
+    /// Grant `roles` (comma separated) on `path` to an API token
+    pub async fn grant_token_role(&self, path: &str, roles: &str, tokenid: &str) -> Result<()> {
+        let params = [
+            ("path", path.to_string()),
+            ("roles", roles.to_string()),
+            ("tokens", tokenid.to_string()),
+        ];
+        let _: serde_json::Value = self.put("/access/acl", &params).await?;
+        Ok(())
+    }
+
+    /// Check that the host answers and the credentials can see something
+    pub async fn check_connection(&self) -> Result<ConnectionReport> {
+        let version: VersionInfo = self.get("/version").await?;
+        let nodes = self.nodes().await?;
+
+        let mut warnings = Vec::new();
+        // /nodes is filtered by permissions, so an empty list means the token can't see anything
+        if nodes.is_empty() {
+            warnings.push("No nodes visible: the token lacks Sys.Audit (grant PVEAuditor on /)".to_string());
         }
 
-        Ok(hosts)
+        Ok(ConnectionReport {
+            version: version.version,
+            nodes: nodes.len(),
+            warnings,
+        })
     }
 
-    pub async fn list_lxc(&self, node: &str) -> Result<Vec<Host>> {
-        let containers: Vec<VmInfo> = self
-            .get(&format!("/nodes/{}/lxc", node))
-            .await
-            .unwrap_or_default();
-
-        let mut hosts = Vec::new();
-        for container in containers {
-            // Try to get IP address from the container's network configuration
-            let ip = self.get_lxc_ip(node, container.vmid).await;
-
-            hosts.push(Host {
-                name: container.name,
-                host_type: HostType::LXC,
-                status: container.status,
-                ip,
-                node: Some(node.to_string()),
-                vmid: Some(container.vmid),
-                ansible_user: Some("gozy".to_string()), // Default user
-            });
-        }
+    /// Nodes of the cluster, including offline ones
+    pub async fn nodes(&self) -> Result<Vec<NodeInfo>> {
+        self.get("/nodes").await
+    }
+
+    /// Cluster name and member node addresses from /cluster/status
+    pub async fn cluster_info(&self) -> Result<ClusterInfo> {
+        let entries: Vec<ClusterStatusEntry> = self.get("/cluster/status").await?;
+
+        let name = entries
+            .iter()
+            .find(|e| e.entry_type == "cluster")
+            .map(|e| e.name.clone());
+        let nodes = entries
+            .into_iter()
+            .filter(|e| e.entry_type == "node")
+            .filter_map(|e| Some((e.name, e.ip?)))
+            .collect();
+
+        Ok(ClusterInfo { name, nodes })
+    }
+
+    /// Fetch every VM and container of the cluster with a single /cluster/resources call
+    pub async fn fetch_inventory(&self) -> Result<Vec<Host>> {
+        let resources: Vec<ClusterResource> = self.get("/cluster/resources?type=vm").await?;
+
+        let hosts = resources
+            .into_iter()
+            .filter_map(|r| {
+                let host_type = match r.resource_type.as_str() {
+                    "qemu" => HostType::VM,
+                    "lxc" => HostType::LXC,
+                    _ => return None,
+                };
+                let vmid = r.vmid?;
+
+                Some(Host {
+                    name: r.name.unwrap_or_else(|| vmid.to_string()),
+                    host_type,
+                    status: r.status.unwrap_or_else(|| "unknown".to_string()),
+                    ip: None,
+                    ip_unavailable: None,
+                    snapshots: None,
+                    node: r.node,
+                    vmid: Some(vmid),
+                    ansible_user: Some("gozy".to_string()), // Default user
+                    cluster: Some(self.name.clone()),
+                    pool: r.pool,
+                    template: r.template == 1,
+                    tags: r
+                        .tags
+                        .map(|t| {
+                            t.split([';', ',', ' '])
+                                .filter(|tag| !tag.is_empty())
+                                .map(|tag| tag.to_string())
+                                .collect()
+                        })
+                        .unwrap_or_default(),
+                    cpu: r.cpu,
+                    mem: r.mem,
+                    maxmem: r.maxmem,
+                    uptime: r.uptime,
+                })
+            })
+            .collect();
 
         Ok(hosts)
     }
 
-    async fn get_vm_ip(&self, node: &str, vmid: u32) -> Option<String> {
+    /// Effective privileges of the credentials used for reading
+    pub async fn permissions(&self) -> Result<Permissions> {
+        self.get("/access/permissions").await
+    }
+
+    /// Resolve guest IPs concurrently, bounded by the cluster's `max_concurrency`.
+    /// Lookups that fail simply leave the IP empty. With known `permissions`,
+    /// guest agent lookups that would be refused are skipped and explained.
+    pub async fn enrich_ips(&self, hosts: &mut [Host], permissions: Option<&Permissions>) {
+        let lookups = hosts.iter().map(|host| async move {
+            match (&host.node, host.vmid) {
+                (Some(node), Some(vmid)) => match host.host_type {
+                    HostType::VM => {
+                        let agent = permissions.is_none_or(|p| p.can_read_guest_agent(vmid, host.pool.as_deref()));
+                        let ip = self.get_vm_ip(node, vmid, agent).await;
+                        let unavailable = (ip.is_none() && !agent).then(|| "no guest-agent permission".to_string());
+                        (ip, unavailable)
+                    }
+                    HostType::LXC => (self.get_lxc_ip(node, vmid).await, None),
+                    HostType::Physical => (None, None),
+                },
+                _ => (None, None),
+            }
+        });
+
+        let ips = futures::future::join_all(lookups).await;
+        for (host, (ip, unavailable)) in hosts.iter_mut().zip(ips) {
+            host.ip = ip;
+            host.ip_unavailable = unavailable;
+        }
+    }
+
+    async fn get_vm_ip(&self, node: &str, vmid: u32, agent: bool) -> Option<String> {
         // Try to get IP from agent network interfaces
-        let interfaces: Result<Vec<NetworkInterface>> = self
-            .get(&format!("/nodes/{}/qemu/{}/agent/network-get-interfaces", node, vmid))
-            .await;
+        let interfaces: Result<Vec<NetworkInterface>> = if agent {
+            self.get(&format!("/nodes/{}/qemu/{}/agent/network-get-interfaces", node, vmid))
+                .await
+        } else {
+            Err(anyhow::anyhow!("No guest agent permission"))
+        };
 
         if let Ok(interfaces) = interfaces {
             for interface in interfaces {
@@ -247,23 +1112,339 @@ impl ProxmoxClient {
         None
     }
 
-    pub async fn fetch_all_hosts(&self) -> Result<Vec<Host>> {
-        let nodes = self.list_nodes().await?;
-        let mut all_hosts = Vec::new();
+    /// Count every guest's snapshots concurrently, reusing fresh counts from
+    /// `cached`; failed lookups leave the count empty
+    pub async fn count_snapshots(&self, hosts: &mut [Host], cached: &CountCache) {
+        let lookups = hosts.iter().map(|host| async move {
+            if let Some(count) = cached.get(&host.id()) {
+                return Some(count);
+            }
+            let (Some(node), Some(vmid)) = (&host.node, host.vmid) else {
+                return None;
+            };
+            let list = self.snapshots(node, &host.host_type, vmid).await.ok()?;
+            Some(snapshots::count(&list))
+        });
 
-        for node in nodes {
-            // Fetch VMs
-            if let Ok(vms) = self.list_vms(&node).await {
-                all_hosts.extend(vms);
+        let counts = futures::future::join_all(lookups).await;
+        for (host, count) in hosts.iter_mut().zip(counts) {
+            host.snapshots = count;
+        }
+    }
+
+    /// Inventory with IPs and snapshot counts, plus the privileges it was
+    /// fetched with (None if they couldn't be read)
+    pub async fn fetch_all_hosts(&self, snapshot_counts: &CountCache) -> Result<(Vec<Host>, Option<Permissions>)> {
+        let mut hosts = self.fetch_inventory().await?;
+        let permissions = self.permissions().await.ok();
+        self.enrich_ips(&mut hosts, permissions.as_ref()).await;
+        self.count_snapshots(&mut hosts, snapshot_counts).await;
+        Ok((hosts, permissions))
+    }
+
+    /// Snapshots of a guest, including the "current" pseudo entry
+    pub async fn snapshots(&self, node: &str, host_type: &HostType, vmid: u32) -> Result<Vec<Snapshot>> {
+        let kind = host_type.api_path().context("Snapshots require a VM or LXC")?;
+        self.get(&format!("/nodes/{}/{}/{}/snapshot", node, kind, vmid)).await
+    }
+
+    /// Start a guest action, returning the UPID of the Proxmox task
+    pub async fn run_action(&self, node: &str, host_type: &HostType, vmid: u32, action: &GuestAction) -> Result<String> {
+        let kind = host_type.api_path().context("Actions require a VM or LXC")?;
+        let base = format!("/nodes/{}/{}/{}", node, kind, vmid);
+
+        match action {
+            GuestAction::Power(action) => self.guest_power(node, host_type, vmid, *action).await,
+            GuestAction::CreateSnapshot { name, description, vmstate } => {
+                let mut params = vec![("snapname", name.clone()), ("description", description.clone())];
+                // Containers have no RAM state to save
+                if *vmstate && *host_type == HostType::VM {
+                    params.push(("vmstate", "1".to_string()));
+                }
+                self.post(&format!("{}/snapshot", base), &params).await
+            }
+            GuestAction::RollbackSnapshot(name) => {
+                self.post(&format!("{}/snapshot/{}/rollback", base, name), &[]).await
+            }
+            GuestAction::DeleteSnapshot(name) => self.delete(&format!("{}/snapshot/{}", base, name)).await,
+            GuestAction::Backup { storage, mode, compress } => {
+                let params = [
+                    ("vmid", vmid.to_string()),
+                    ("storage", storage.clone()),
+                    ("mode", mode.as_str().to_string()),
+                    ("compress", compress.as_str().to_string()),
+                ];
+                self.post(&format!("/nodes/{}/vzdump", node), &params).await
+            }
+            GuestAction::Restore { archive, vmid: new_vmid } => {
+                // VMs restore from `archive`, containers from an `ostemplate` with restore=1.
+                // `unique` gives the copy new MAC addresses so it can run next to the original.
+                let mut params = vec![("vmid", new_vmid.to_string()), ("unique", "1".to_string())];
+                match host_type {
+                    HostType::LXC => {
+                        params.push(("ostemplate", archive.clone()));
+                        params.push(("restore", "1".to_string()));
+                    }
+                    _ => params.push(("archive", archive.clone())),
+                }
+                self.post(&format!("/nodes/{}/{}", node, kind), &params).await
+            }
+            GuestAction::Migrate { target, running } => {
+                let mut params = vec![("target", target.clone())];
+                if *running {
+                    match host_type {
+                        // Local disks are copied along; guests on shared storage are unaffected
+                        HostType::VM => {
+                            params.push(("online", "1".to_string()));
+                            params.push(("with-local-disks", "1".to_string()));
+                        }
+                        // Containers can't move live: stop, migrate, start again
+                        _ => params.push(("restart", "1".to_string())),
+                    }
+                }
+                self.post(&format!("{}/migrate", base), &params).await
+            }
+            GuestAction::Clone(spec) => {
+                let mut params = vec![
+                    ("newid", spec.vmid.to_string()),
+                    ("target", spec.target.clone()),
+                    ("full", if spec.full { "1" } else { "0" }.to_string()),
+                ];
+                if !spec.name.is_empty() {
+                    // Containers call it hostname
+                    let key = if *host_type == HostType::LXC { "hostname" } else { "name" };
+                    params.push((key, spec.name.clone()));
+                }
+                if let Some(storage) = spec.storage.as_ref().filter(|_| spec.full) {
+                    params.push(("storage", storage.clone()));
+                }
+                if let Some(pool) = &spec.pool {
+                    params.push(("pool", pool.clone()));
+                }
+                self.post(&format!("{}/clone", base), &params).await
             }
+        }
+    }
 
-            // Fetch LXC containers
-            if let Ok(lxc) = self.list_lxc(&node).await {
-                all_hosts.extend(lxc);
+    /// Apply the cloud-init settings of a finished clone
+    pub async fn configure_clone(&self, host_type: &HostType, spec: &CloneSpec) -> Result<()> {
+        if *host_type != HostType::VM {
+            return Ok(());
+        }
+        let mut params = Vec::new();
+        if let Some(user) = &spec.ciuser {
+            params.push(("ciuser", user.clone()));
+        }
+        if let Some(keys) = &spec.sshkeys {
+            // Proxmox wants the keys URL-encoded on top of the form encoding
+            params.push(("sshkeys", encode_uri_component(keys)));
+        }
+        if let Some(ipconfig) = &spec.ipconfig0 {
+            params.push(("ipconfig0", ipconfig.clone()));
+        }
+        if params.is_empty() {
+            return Ok(());
+        }
+        let _: serde_json::Value = self
+            .put(&format!("/nodes/{}/qemu/{}/config", spec.target, spec.vmid), &params)
+            .await?;
+        Ok(())
+    }
+
+    /// Enabled storages on `node` that hold `content` (backup, vztmpl, rootdir, ...)
+    pub async fn storages(&self, node: &str, content: &str) -> Result<Vec<StorageInfo>> {
+        self.get(&format!("/nodes/{}/storage?content={}&enabled=1", node, content)).await
+    }
+
+    /// Storages on `node` that can hold backups
+    pub async fn backup_storages(&self, node: &str) -> Result<Vec<StorageInfo>> {
+        self.storages(node, "backup").await
+    }
+
+    /// OS templates and root disk storages `node` offers for new containers
+    pub async fn container_options(&self, node: &str) -> Result<NodeOptions> {
+        let template_storages = self.storages(node, "vztmpl").await?;
+        let listings = template_storages.iter().map(|storage| async move {
+            let path = format!("/nodes/{}/storage/{}/content?content=vztmpl", node, storage.storage);
+            self.get::<Vec<StorageContent>>(&path).await
+        });
+
+        let mut templates: Vec<String> = futures::future::join_all(listings)
+            .await
+            .into_iter()
+            // A storage that can't be listed just contributes no templates
+            .filter_map(|listing| listing.ok())
+            .flatten()
+            .map(|content| content.volid)
+            .collect();
+        templates.sort();
+
+        Ok(NodeOptions {
+            templates,
+            storages: self.storages(node, "rootdir").await?,
+        })
+    }
+
+    /// Create a container, returning the UPID of the Proxmox task
+    pub async fn create_container(&self, node: &str, spec: &ContainerSpec) -> Result<String> {
+        self.post(&format!("/nodes/{}/lxc", node), &spec.params()).await
+    }
+
+    /// Backups of a guest on every backup storage `node` can see, newest first.
+    /// Storages that can't be listed are skipped unless all of them fail.
+    pub async fn backups(&self, node: &str, vmid: u32) -> Result<Vec<Backup>> {
+        let storages = self.backup_storages(node).await?;
+        let listings = storages.iter().map(|storage| async move {
+            let path = format!("/nodes/{}/storage/{}/content?content=backup&vmid={}", node, storage.storage, vmid);
+            self.get::<Vec<Backup>>(&path).await
+        });
+
+        let mut found = Vec::new();
+        let mut last_error = None;
+        for result in futures::future::join_all(listings).await {
+            match result {
+                Ok(list) => found.extend(list),
+                Err(e) => last_error = Some(e),
             }
         }
+        match last_error {
+            Some(e) if found.is_empty() => Err(e),
+            _ => Ok(backups::for_guest(found, vmid)),
+        }
+    }
 
-        Ok(all_hosts)
+    /// Lowest free vmid in the cluster
+    pub async fn next_vmid(&self) -> Result<u32> {
+        // Returned as a string by most Proxmox versions
+        let id: serde_json::Value = self.get("/cluster/nextid").await?;
+        match &id {
+            serde_json::Value::String(s) => s.parse().ok(),
+            other => other.as_u64().and_then(|n| u32::try_from(n).ok()),
+        }
+        .with_context(|| format!("Unexpected next vmid: {}", id))
+    }
+
+    /// Trigger a power action on a guest, returning the UPID of the Proxmox task
+    pub async fn guest_power(&self, node: &str, host_type: &HostType, vmid: u32, action: PowerAction) -> Result<String> {
+        let kind = host_type.api_path().context("Power actions require a VM or LXC")?;
+        self.post(&format!("/nodes/{}/{}/{}/status/{}", node, kind, vmid, action.as_str()), &[])
+            .await
+    }
+
+    pub async fn task_status(&self, node: &str, upid: &str) -> Result<TaskStatus> {
+        self.get(&format!("/nodes/{}/tasks/{}/status", node, upid)).await
+    }
+
+    /// Task log lines starting at line `start`
+    pub async fn task_log(&self, node: &str, upid: &str, start: usize) -> Result<Vec<String>> {
+        let lines: Vec<TaskLogLine> = self
+            .get(&format!("/nodes/{}/tasks/{}/log?start={}&limit=500", node, upid, start))
+            .await?;
+        Ok(lines.into_iter().map(|l| l.t).collect())
+    }
+
+    /// Current status of a guest, reporting paused VMs as such instead of "running"
+    pub async fn guest_status(&self, node: &str, host_type: &HostType, vmid: u32) -> Result<String> {
+        let kind = host_type.api_path().context("Status requires a VM or LXC")?;
+        let status: GuestStatus = self
+            .get(&format!("/nodes/{}/{}/{}/status/current", node, kind, vmid))
+            .await?;
+
+        Ok(match status.qmpstatus {
+            Some(qmp) if qmp == "paused" || qmp == "suspended" => qmp,
+            _ => status.status,
+        })
     }
 }
 
+/// Percent-encode everything but unreserved characters, like JavaScript's encodeURIComponent
+fn encode_uri_component(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'!' | b'~' | b'*' | b'\'' | b'(' | b')' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_uri_component_for_ssh_keys() {
+        assert_eq!(
+            encode_uri_component("ssh-ed25519 AAAA+b/c= me@host\n"),
+            "ssh-ed25519%20AAAA%2Bb%2Fc%3D%20me%40host%0A"
+        );
+    }
+
+    #[test]
+    fn test_pin_only_applies_to_its_host() {
+        let pve_host = ProxmoxHost {
+            host: "10.0.0.1".to_string(),
+            endpoints: vec!["10.0.0.2".to_string()],
+            fingerprint: Some("AB:CD".to_string()),
+            ..Default::default()
+        };
+
+        let pins: Vec<_> = pve_host
+            .addresses()
+            .into_iter()
+            .map(|(host, port)| endpoint_pin(&pve_host, &host, port))
+            .collect();
+        assert_eq!(pins, vec![Some("AB:CD"), None]);
+        assert_eq!(endpoint_pin(&pve_host, "10.0.0.1", 8007), None);
+    }
+
+    #[test]
+    fn test_cluster_status_from_http_error() {
+        let unauthorized: anyhow::Error = HttpError {
+            status: reqwest::StatusCode::UNAUTHORIZED,
+            body: String::new(),
+        }
+        .into();
+        assert_eq!(ClusterStatus::from_error(&unauthorized), ClusterStatus::AuthFailed);
+
+        let server_error = anyhow::Error::from(HttpError {
+            status: reqwest::StatusCode::INTERNAL_SERVER_ERROR,
+            body: String::new(),
+        })
+        .context("Error fetching inventory");
+        assert_eq!(ClusterStatus::from_error(&server_error), ClusterStatus::Error);
+
+        assert_eq!(ClusterStatus::from_error(&anyhow::anyhow!("boom")), ClusterStatus::Error);
+    }
+
+    #[test]
+    fn test_operator_login_is_separate_from_viewer_token() {
+        let sessions = SessionStore::default();
+        let pve_host = ProxmoxHost {
+            name: "vs01".to_string(),
+            host: "10.0.0.1".to_string(),
+            api_token_id: "monitor@pve!proxmon".to_string(),
+            api_token_secret: "secret".to_string(),
+            operator_username: "admin".to_string(),
+            ..Default::default()
+        };
+        let client = ProxmoxClient::new(&pve_host, &sessions).unwrap();
+
+        assert!(client.is_logged_in(Credential::Viewer));
+        assert!(!client.is_logged_in(Credential::Operator));
+        assert_eq!(client.ticket_user(Credential::Operator).map(|(_, user, _)| user), Some("admin@pam"));
+
+        sessions.set(
+            &Credential::Operator.session_key("vs01"),
+            crate::auth::Ticket {
+                username: "admin@pam".to_string(),
+                ticket: "PVE:admin@pam:1234".to_string(),
+                csrf_token: String::new(),
+                issued: std::time::Instant::now(),
+            },
+        );
+        assert!(client.is_logged_in(Credential::Operator));
+    }
+}
@@ -0,0 +1,132 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use crate::proxmox::HostId;
+
+// Name of the pseudo snapshot Proxmox lists for the guest's live state
+pub const CURRENT: &str = "current";
+
+/// One entry of /nodes/{node}/{qemu|lxc}/{vmid}/snapshot
+#[derive(Debug, Clone, Deserialize)]
+pub struct Snapshot {
+    pub name: String,
+    pub parent: Option<String>,
+    #[serde(default)]
+    pub description: String,
+    // Unix time the snapshot was taken; not set on "current"
+    pub snaptime: Option<u64>,
+    // 1 when the VM's RAM was saved along with the disks
+    #[serde(default)]
+    pub vmstate: u8,
+}
+
+impl Snapshot {
+    pub fn is_current(&self) -> bool {
+        self.name == CURRENT
+    }
+}
+
+/// Number of real snapshots, not counting "current"
+pub fn count(snapshots: &[Snapshot]) -> usize {
+    snapshots.iter().filter(|s| !s.is_current()).count()
+}
+
+// How long a count is reused before a refresh asks Proxmox again
+const COUNT_TTL: Duration = Duration::from_secs(600);
+
+/// Snapshot counts from earlier refreshes. Counting costs one request per guest,
+/// so a refresh only asks for the counts that are missing or older than COUNT_TTL.
+#[derive(Debug, Clone, Default)]
+pub struct CountCache {
+    counts: HashMap<HostId, (usize, Instant)>,
+}
+
+impl CountCache {
+    /// The cached count of a guest, if it is still fresh
+    pub fn get(&self, id: &HostId) -> Option<usize> {
+        self.counts
+            .get(id)
+            .filter(|(_, counted)| counted.elapsed() < COUNT_TTL)
+            .map(|(count, _)| *count)
+    }
+
+    pub fn insert(&mut self, id: HostId, count: usize) {
+        self.counts.insert(id, (count, Instant::now()));
+    }
+
+    /// Forget a guest's count, e.g. after its snapshots changed
+    pub fn invalidate(&mut self, id: &HostId) {
+        self.counts.remove(id);
+    }
+}
+
+/// Snapshots in tree order (parents before their children, oldest first) with
+/// their depth. "current" ends up below the snapshot the guest runs from.
+pub fn tree(snapshots: &[Snapshot]) -> Vec<(usize, &Snapshot)> {
+    let is_root = |s: &Snapshot| {
+        s.parent
+            .as_ref()
+            .is_none_or(|parent| !snapshots.iter().any(|other| &other.name == parent))
+    };
+
+    let mut ordered = Vec::new();
+    // Depth-first, pushing children in reverse so the oldest comes out first
+    let mut stack: Vec<(usize, &Snapshot)> = sorted(snapshots.iter().filter(|s| is_root(s)))
+        .into_iter()
+        .rev()
+        .map(|s| (0, s))
+        .collect();
+    while let Some((depth, snapshot)) = stack.pop() {
+        ordered.push((depth, snapshot));
+        let children = snapshots
+            .iter()
+            .filter(|s| s.parent.as_deref() == Some(snapshot.name.as_str()));
+        stack.extend(sorted(children).into_iter().rev().map(|s| (depth + 1, s)));
+    }
+    ordered
+}
+
+// Oldest first, with "current" (which has no time) last
+fn sorted<'a>(snapshots: impl Iterator<Item = &'a Snapshot>) -> Vec<&'a Snapshot> {
+    let mut snapshots: Vec<_> = snapshots.collect();
+    snapshots.sort_by_key(|s| s.snaptime.unwrap_or(u64::MAX));
+    snapshots
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tree_follows_parent_links() {
+        let snapshots: Vec<Snapshot> = serde_json::from_str(
+            r#"[
+                {"name": "current", "parent": "after-fix", "description": "You are here!"},
+                {"name": "after-fix", "parent": "base", "snaptime": 300},
+                {"name": "experiment", "parent": "base", "snaptime": 200, "vmstate": 1},
+                {"name": "base", "snaptime": 100, "description": "Fresh install"}
+            ]"#,
+        )
+        .unwrap();
+
+        let tree: Vec<(usize, &str)> = tree(&snapshots).into_iter().map(|(d, s)| (d, s.name.as_str())).collect();
+        assert_eq!(
+            tree,
+            vec![(0, "base"), (1, "experiment"), (1, "after-fix"), (2, "current")]
+        );
+        assert_eq!(count(&snapshots), 3);
+    }
+
+    #[test]
+    fn test_count_cache_invalidate() {
+        let id = HostId::Guest { cluster: "vs01".to_string(), vmid: 100 };
+        let mut cache = CountCache::default();
+        assert_eq!(cache.get(&id), None);
+
+        cache.insert(id.clone(), 3);
+        assert_eq!(cache.get(&id), Some(3));
+        cache.invalidate(&id);
+        assert_eq!(cache.get(&id), None);
+    }
+}
@@ -0,0 +1,257 @@
+use anyhow::Result;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::events::{AppEvent, EventSender};
+use crate::proxmox::ProxmoxClient;
+
+// How often a followed task is polled for status and new log lines
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+// Failed polls in a row before a task is given up on; the wait doubles after each
+const MAX_POLL_FAILURES: u32 = 5;
+const MAX_POLL_BACKOFF: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TaskState {
+    Running,
+    Ok,
+    // Finished, but logged warnings (e.g. "WARNINGS: 2" from vzdump)
+    Warnings(String),
+    Failed(String),
+}
+
+impl TaskState {
+    pub fn as_str(&self) -> &str {
+        match self {
+            TaskState::Running => "running",
+            TaskState::Ok => "OK",
+            TaskState::Warnings(exit) | TaskState::Failed(exit) => exit,
+        }
+    }
+
+    /// State of a stopped task from its Proxmox exit status
+    fn from_exitstatus(exitstatus: Option<&str>) -> Self {
+        match exitstatus {
+            Some("OK") | None => TaskState::Ok,
+            Some(exit) if exit.starts_with("WARNINGS:") => TaskState::Warnings(exit.to_string()),
+            Some(exit) => TaskState::Failed(exit.to_string()),
+        }
+    }
+}
+
+/// The pieces of a Proxmox UPID (`UPID:node:pid:pstart:starttime:type:id:user:`)
+#[derive(Debug, Clone, PartialEq)]
+pub struct Upid {
+    pub node: String,
+    pub starttime: u64,
+    pub task_type: String,
+    pub id: String,
+    pub user: String,
+}
+
+impl Upid {
+    pub fn parse(upid: &str) -> Option<Self> {
+        let parts: Vec<&str> = upid.split(':').collect();
+        if parts.len() < 8 || parts[0] != "UPID" {
+            return None;
+        }
+
+        Some(Self {
+            node: parts[1].to_string(),
+            starttime: u64::from_str_radix(parts[4], 16).ok()?,
+            task_type: parts[5].to_string(),
+            id: parts[6].to_string(),
+            user: parts[7].to_string(),
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TrackedTask {
+    pub upid: String,
+    pub cluster: String,
+    pub node: String,
+    pub task_type: String,
+    pub target: String,
+    pub user: String,
+    pub starttime: u64,
+    pub description: String,
+    pub state: TaskState,
+    pub log: Vec<String>,
+}
+
+impl TrackedTask {
+    /// Human readable time since the task was started, e.g. "3m ago"
+    pub fn age(&self) -> String {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        format_age(now.saturating_sub(self.starttime))
+    }
+}
+
+pub fn format_age(secs: u64) -> String {
+    if secs < 60 {
+        format!("{}s ago", secs)
+    } else if secs < 3600 {
+        format!("{}m ago", secs / 60)
+    } else if secs < 86400 {
+        format!("{}h ago", secs / 3600)
+    } else {
+        format!("{}d ago", secs / 86400)
+    }
+}
+
+#[derive(Debug)]
+pub enum TaskUpdate {
+    Started(TrackedTask),
+    State { upid: String, state: TaskState },
+    Log { upid: String, lines: Vec<String> },
+}
+
+/// Cloneable sender used by background work to report tasks to the tracker
+#[derive(Clone)]
+pub struct TaskHandle {
+    tx: EventSender,
+}
+
+impl TaskHandle {
+    pub fn new(tx: EventSender) -> Self {
+        Self { tx }
+    }
+
+    fn send(&self, update: TaskUpdate) {
+        let _ = self.tx.send(AppEvent::Task(update));
+    }
+
+    /// Register a task and poll it until it finishes, streaming status and log
+    /// lines to the tracker. Fails with the Proxmox exit status if the task did not
+    /// end with "OK" or "WARNINGS: n", or once the task status can't be read several times in a row.
+    pub async fn follow(&self, client: &ProxmoxClient, cluster: &str, upid: &str, description: String) -> Result<()> {
+        let parsed = Upid::parse(upid);
+        let node = match &parsed {
+            Some(p) => p.node.clone(),
+            None => anyhow::bail!("Unexpected task id from Proxmox: {}", upid),
+        };
+
+        self.send(TaskUpdate::Started(TrackedTask {
+            upid: upid.to_string(),
+            cluster: cluster.to_string(),
+            node: node.clone(),
+            task_type: parsed.as_ref().map(|p| p.task_type.clone()).unwrap_or_default(),
+            target: parsed.as_ref().map(|p| p.id.clone()).unwrap_or_default(),
+            user: parsed.as_ref().map(|p| p.user.clone()).unwrap_or_default(),
+            starttime: parsed.as_ref().map(|p| p.starttime).unwrap_or(0),
+            description,
+            state: TaskState::Running,
+            log: Vec::new(),
+        }));
+
+        let mut log_offset = 0;
+        let mut failures = 0;
+        loop {
+            // A timeout or failover mid-task is no reason to stop following it
+            let status = match client.task_status(&node, upid).await {
+                Ok(status) => {
+                    failures = 0;
+                    status
+                }
+                Err(e) => {
+                    failures += 1;
+                    if failures >= MAX_POLL_FAILURES {
+                        let reason = format!("lost track of task: {:#}", e);
+                        self.send(TaskUpdate::State {
+                            upid: upid.to_string(),
+                            state: TaskState::Failed(reason.clone()),
+                        });
+                        anyhow::bail!(reason);
+                    }
+                    tokio::time::sleep((POLL_INTERVAL * 2u32.pow(failures)).min(MAX_POLL_BACKOFF)).await;
+                    continue;
+                }
+            };
+
+            // Fetch log lines before reporting the final state so the log is complete
+            if let Ok(lines) = client.task_log(&node, upid, log_offset).await {
+                if !lines.is_empty() {
+                    log_offset += lines.len();
+                    self.send(TaskUpdate::Log {
+                        upid: upid.to_string(),
+                        lines,
+                    });
+                }
+            }
+
+            if status.status == "stopped" {
+                let state = TaskState::from_exitstatus(status.exitstatus.as_deref());
+                self.send(TaskUpdate::State {
+                    upid: upid.to_string(),
+                    state: state.clone(),
+                });
+
+                return match state {
+                    TaskState::Failed(exit) => anyhow::bail!("{}", exit),
+                    _ => Ok(()),
+                };
+            }
+
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct TaskTracker {
+    pub tasks: Vec<TrackedTask>,
+}
+
+impl TaskTracker {
+    pub fn running_count(&self) -> usize {
+        self.tasks.iter().filter(|t| t.state == TaskState::Running).count()
+    }
+
+    /// Apply an update sent by a background poller
+    pub fn apply(&mut self, update: TaskUpdate) {
+        match update {
+            TaskUpdate::Started(task) => {
+                // Newest first
+                self.tasks.insert(0, task);
+            }
+            TaskUpdate::State { upid, state } => {
+                if let Some(task) = self.tasks.iter_mut().find(|t| t.upid == upid) {
+                    task.state = state;
+                }
+            }
+            TaskUpdate::Log { upid, lines } => {
+                if let Some(task) = self.tasks.iter_mut().find(|t| t.upid == upid) {
+                    task.log.extend(lines);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_upid() {
+        let upid = Upid::parse("UPID:pve1:000B3C8F:0A3E5C29:65A1B2C3:qmshutdown:100:root@pam:").unwrap();
+
+        assert_eq!(upid.node, "pve1");
+        assert_eq!(upid.starttime, 0x65A1B2C3);
+        assert_eq!(upid.task_type, "qmshutdown");
+        assert_eq!(upid.id, "100");
+        assert_eq!(upid.user, "root@pam");
+
+        assert!(Upid::parse("not-a-upid").is_none());
+    }
+
+    #[test]
+    fn test_warnings_are_not_failures() {
+        assert_eq!(TaskState::from_exitstatus(Some("OK")), TaskState::Ok);
+        assert_eq!(TaskState::from_exitstatus(Some("WARNINGS: 2")), TaskState::Warnings("WARNINGS: 2".to_string()));
+        assert_eq!(TaskState::from_exitstatus(Some("job errors")), TaskState::Failed("job errors".to_string()));
+    }
+}
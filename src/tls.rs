@@ -0,0 +1,150 @@
+use anyhow::{Context, Result};
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::crypto::{verify_tls12_signature, verify_tls13_signature, CryptoProvider};
+use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use rustls::{DigitallySignedStruct, SignatureScheme};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// SHA-256 fingerprint of a DER certificate, formatted like the Proxmox web UI
+/// shows it ("AB:CD:...")
+pub fn fingerprint(der: &[u8]) -> String {
+    ring::digest::digest(&ring::digest::SHA256, der)
+        .as_ref()
+        .iter()
+        .map(|b| format!("{:02X}", b))
+        .collect::<Vec<_>>()
+        .join(":")
+}
+
+/// Compare fingerprints ignoring case and separators
+pub fn fingerprints_match(a: &str, b: &str) -> bool {
+    let normalize = |s: &str| {
+        s.chars()
+            .filter(|c| c.is_ascii_hexdigit())
+            .map(|c| c.to_ascii_lowercase())
+            .collect::<String>()
+    };
+    normalize(a) == normalize(b)
+}
+
+/// Accepts exactly one server certificate, identified by its fingerprint, no
+/// matter who signed it. With `pin` unset every certificate is accepted and its
+/// fingerprint recorded, which is how the Setup form learns what to pin.
+#[derive(Debug)]
+struct FingerprintVerifier {
+    pin: Option<String>,
+    seen: Arc<Mutex<Option<String>>>,
+    provider: Arc<CryptoProvider>,
+}
+
+impl ServerCertVerifier for FingerprintVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        let presented = fingerprint(end_entity);
+        if let Ok(mut seen) = self.seen.lock() {
+            *seen = Some(presented.clone());
+        }
+
+        match &self.pin {
+            Some(pin) if !fingerprints_match(pin, &presented) => Err(rustls::Error::General(format!(
+                "certificate fingerprint mismatch: pinned {}, server presented {}",
+                pin, presented
+            ))),
+            _ => Ok(ServerCertVerified::assertion()),
+        }
+    }
+
+    // The handshake signatures are still checked, so a pinned certificate can't be
+    // replayed by someone without its private key
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls12_signature(message, cert, dss, &self.provider.signature_verification_algorithms)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls13_signature(message, cert, dss, &self.provider.signature_verification_algorithms)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.provider.signature_verification_algorithms.supported_schemes()
+    }
+}
+
+fn fingerprint_config(pin: Option<String>, seen: Arc<Mutex<Option<String>>>) -> Result<rustls::ClientConfig> {
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let verifier = FingerprintVerifier {
+        pin,
+        seen,
+        provider: provider.clone(),
+    };
+
+    let config = rustls::ClientConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()
+        .context("Failed to set up TLS")?
+        .dangerous()
+        .with_custom_certificate_verifier(Arc::new(verifier))
+        .with_no_client_auth();
+    Ok(config)
+}
+
+/// TLS settings that only trust the certificate with the given fingerprint
+pub fn pinned_config(pin: &str) -> Result<rustls::ClientConfig> {
+    fingerprint_config(Some(pin.to_string()), Arc::new(Mutex::new(None)))
+}
+
+/// Connect to `host:port`, through `proxy` if given, and return the fingerprint
+/// of the certificate it presents
+pub async fn fetch_fingerprint(host: &str, port: u16, proxy: Option<&str>) -> Result<String> {
+    let seen = Arc::new(Mutex::new(None));
+    let mut builder = reqwest::Client::builder()
+        .use_preconfigured_tls(fingerprint_config(None, seen.clone())?)
+        .connect_timeout(Duration::from_secs(5));
+    // TLS runs end to end through the tunnel, so the proxy doesn't change the certificate
+    if let Some(proxy) = proxy {
+        let proxy = reqwest::Proxy::all(proxy).with_context(|| format!("Invalid proxy URL '{}'", proxy))?;
+        builder = builder.proxy(proxy);
+    }
+    let http = builder.build().context("Failed to create HTTP client")?;
+
+    let host = if host.contains(':') { format!("[{}]", host) } else { host.to_string() };
+    // Any answer will do, only the handshake matters
+    let _ = http
+        .get(format!("https://{}:{}/", host, port))
+        .send()
+        .await
+        .context("Failed to connect")?;
+
+    let seen = seen.lock().ok().and_then(|s| s.clone());
+    seen.context("Server did not present a certificate")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fingerprint_format_and_match() {
+        let fp = fingerprint(b"not really a certificate");
+
+        assert_eq!(fp.len(), 32 * 3 - 1);
+        assert!(fp.chars().all(|c| c == ':' || c.is_ascii_hexdigit()));
+        assert!(fingerprints_match(&fp, &fp.replace(':', "").to_lowercase()));
+        assert!(!fingerprints_match(&fp, &fingerprint(b"another certificate")));
+    }
+}
@@ -6,12 +6,21 @@ use ratatui::{
     Frame,
 };
 
-use crate::app::{App, ViewMode, SortColumn, SortDirection, SetupField};
+use crate::app::{App, ViewMode, SortColumn, SortDirection, SetupField, SetupMethod, SnapshotField, BackupField, CloneField, ContainerField, ContainerStep, HostFilter};
+use crate::auth::Credential;
+use crate::containers::{self, Network};
+use crate::permissions;
+use crate::proxmox::{self, ClusterStatus, GuestAction, Host, HostType, PowerAction};
+use crate::tasks::{format_age, TaskState};
 
 pub fn render(f: &mut Frame, app: &mut App) {
-    // Show fullscreen loader during initial load (when loading and no hosts yet)
-    if app.is_loading && app.hosts.is_empty() && !app.initial_fetch_done {
-        render_loading_screen(f, app);
+    // Show fullscreen loader until the initial fetch completes
+    if !app.initial_fetch_done {
+        if app.view_mode == ViewMode::Login {
+            render_login_view(f, app);
+        } else {
+            render_loading_screen(f, app);
+        }
         return;
     }
 
@@ -20,6 +29,19 @@ pub fn render(f: &mut Frame, app: &mut App) {
         ViewMode::Export => render_export_view(f, app),
         ViewMode::EditIp => render_edit_ip_view(f, app),
         ViewMode::Setup => render_setup_view(f, app),
+        ViewMode::ConfirmAction => render_confirm_action_view(f, app),
+        ViewMode::Tasks => render_tasks_view(f, app),
+        ViewMode::TaskLog => render_task_log_view(f, app),
+        ViewMode::Clusters => render_clusters_view(f, app),
+        ViewMode::Login => render_login_view(f, app),
+        ViewMode::Snapshots => render_snapshots_view(f, app),
+        ViewMode::SnapshotCreate => render_snapshot_create_view(f, app),
+        ViewMode::Backups => render_backups_view(f, app),
+        ViewMode::BackupCreate => render_backup_create_view(f, app),
+        ViewMode::BackupRestore => render_restore_view(f, app),
+        ViewMode::Migrate => render_migrate_view(f, app),
+        ViewMode::Clone => render_clone_view(f, app),
+        ViewMode::ContainerCreate => render_container_view(f, app),
     }
 }
 
@@ -29,13 +51,19 @@ fn render_main_view(f: &mut Frame, app: &mut App) {
         .constraints([
             Constraint::Length(3), // Title
             Constraint::Min(0),     // Main content
-            Constraint::Length(3),  // Status/keybindings
+            Constraint::Length(5),  // Status/keybindings
         ])
         .split(f.area());
 
     // Title
-    let title = Paragraph::new("Proxmon - Gotta manage 'em all!")
-        .style(Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD))
+    let mut title_spans = vec![Span::styled(
+        "Proxmon - Gotta manage 'em all!",
+        Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD),
+    )];
+    if proxmox::is_read_only() {
+        title_spans.push(Span::styled("  [READ-ONLY]", Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)));
+    }
+    let title = Paragraph::new(Line::from(title_spans))
         .block(Block::default().borders(Borders::ALL));
     f.render_widget(title, chunks[0]);
 
@@ -62,21 +90,46 @@ fn render_main_view(f: &mut Frame, app: &mut App) {
         Cell::from(text).style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))
     });
 
+    // Resource columns aren't sortable, so they don't carry a SortColumn
+    let header_cells = header_cells.chain(["CPU", "Memory", "Uptime", "Snaps"].iter().map(|label| {
+        Cell::from(*label).style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))
+    }));
+
     let header = Row::new(header_cells).height(1).bottom_margin(1);
 
-    let rows = app.hosts.iter().map(|host| {
+    let rows = app.visible_hosts().map(|host| {
+        let marked = app.marked.contains(&host.id());
         let cells = vec![
-            Cell::from(host.name.clone()),
+            if marked {
+                Cell::from(format!("● {}", host.name)).style(Style::default().fg(Color::Cyan))
+            } else {
+                Cell::from(host.name.clone())
+            },
             Cell::from(host.host_type.as_str()),
-            Cell::from(host.status.clone()).style(
-                if host.status == "running" {
-                    Style::default().fg(Color::Green)
-                } else {
-                    Style::default().fg(Color::Red)
-                }
-            ),
-            Cell::from(host.ip.clone().unwrap_or_else(|| "N/A".to_string())),
+            if host.template {
+                Cell::from("template").style(Style::default().fg(Color::Blue))
+            } else {
+                Cell::from(host.status.clone()).style(
+                    if host.status == "running" {
+                        Style::default().fg(Color::Green)
+                    } else {
+                        Style::default().fg(Color::Red)
+                    }
+                )
+            },
+            match (&host.ip, &host.ip_unavailable) {
+                (Some(ip), _) => Cell::from(ip.clone()),
+                (None, Some(reason)) => Cell::from(reason.clone()).style(Style::default().fg(Color::DarkGray)),
+                (None, None) => Cell::from("N/A"),
+            },
             Cell::from(host.node.clone().unwrap_or_else(|| "-".to_string())),
+            Cell::from(host.cpu.map(|c| format!("{:.1}%", c * 100.0)).unwrap_or_else(|| "-".to_string())),
+            Cell::from(match (host.mem, host.maxmem) {
+                (Some(mem), Some(maxmem)) if maxmem > 0 => format!("{} / {}", format_bytes(mem), format_bytes(maxmem)),
+                _ => "-".to_string(),
+            }),
+            Cell::from(host.uptime.filter(|u| *u > 0).map(format_uptime).unwrap_or_else(|| "-".to_string())),
+            Cell::from(host.snapshots.map(|n| n.to_string()).unwrap_or_else(|| "-".to_string())),
         ];
         Row::new(cells).height(1)
     });
@@ -84,15 +137,22 @@ fn render_main_view(f: &mut Frame, app: &mut App) {
     let table = Table::new(
         rows,
         [
-            Constraint::Percentage(25),
+            Constraint::Percentage(18),
+            Constraint::Percentage(7),
             Constraint::Percentage(10),
             Constraint::Percentage(15),
-            Constraint::Percentage(25),
-            Constraint::Percentage(25),
+            Constraint::Percentage(12),
+            Constraint::Percentage(8),
+            Constraint::Percentage(15),
+            Constraint::Percentage(10),
+            Constraint::Percentage(5),
         ],
     )
     .header(header)
-    .block(Block::default().borders(Borders::ALL).title("Hosts"))
+    .block(Block::default().borders(Borders::ALL).title(match app.marked.len() {
+        0 => hosts_title(app),
+        n => format!("{} - {} marked (Esc clears)", hosts_title(app), n),
+    }))
     .row_highlight_style(
         Style::default()
             .bg(Color::DarkGray)
@@ -105,28 +165,33 @@ fn render_main_view(f: &mut Frame, app: &mut App) {
 
     f.render_stateful_widget(table, chunks[1], &mut table_state);
 
-    // Status bar and keybindings
-    let status_text = if app.is_loading {
-        "Loading..."
-    } else if let Some(ref error) = app.last_error {
-        error.as_str()
-    } else {
-        "Ready"
-    };
+    // Status bar and keybindings. A refresh in progress is shown next to the last
+    // message, which would otherwise hide it.
+    let mut status_spans = vec![Span::styled("Status: ", Style::default().add_modifier(Modifier::BOLD))];
+    if app.is_loading {
+        status_spans.push(Span::styled("Refreshing...", Style::default().fg(Color::Yellow)));
+    }
+    match &app.last_error {
+        Some(message) => {
+            if app.is_loading {
+                status_spans.push(Span::raw(" | "));
+            }
+            status_spans.push(Span::styled(message.as_str(), Style::default().fg(Color::Red)));
+        }
+        None if !app.is_loading => status_spans.push(Span::styled("Ready", Style::default().fg(Color::Green))),
+        None => {}
+    }
 
-    let status_style = if app.last_error.is_some() {
-        Style::default().fg(Color::Red)
-    } else if app.is_loading {
-        Style::default().fg(Color::Yellow)
-    } else {
-        Style::default().fg(Color::Green)
-    };
+    // Actions that can't be performed (read-only mode, missing privileges) are greyed out
+    let selected = app.selected_host();
+    let power_key = action_key(app, selected, GuestAction::Power(PowerAction::Start).privilege());
+    let backup_key = action_key(app, selected, permissions::VM_BACKUP);
+    let migrate_key = action_key(app, selected, permissions::VM_MIGRATE);
+    let clone_key = action_key(app, selected, permissions::VM_CLONE);
+    let create_key = key_style(!proxmox::is_read_only() && app.can_create_guest());
 
     let bottom_text = vec![
-        Line::from(vec![
-            Span::styled("Status: ", Style::default().add_modifier(Modifier::BOLD)),
-            Span::styled(status_text, status_style),
-        ]),
+        Line::from(status_spans),
         Line::from(vec![
             Span::styled("q", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
             Span::raw(": Quit | "),
@@ -141,7 +206,49 @@ fn render_main_view(f: &mut Frame, app: &mut App) {
             Span::styled("e", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
             Span::raw(": Export | "),
             Span::styled("r", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
-            Span::raw(": Refresh"),
+            Span::raw(": Refresh | "),
+            Span::styled("p", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+            Span::raw(": Pause auto refresh"),
+        ]),
+        Line::from(vec![
+            Span::styled("s", power_key),
+            Span::raw(": Start | "),
+            Span::styled("x", power_key),
+            Span::raw(": Shutdown | "),
+            Span::styled("X", power_key),
+            Span::raw(": Stop | "),
+            Span::styled("b", power_key),
+            Span::raw(": Reboot | "),
+            Span::styled("z", power_key),
+            Span::raw(": Suspend | "),
+            Span::styled("u", power_key),
+            Span::raw(": Resume | "),
+            Span::styled("t", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+            Span::raw(format!(": Tasks ({} running) | ", app.tasks.running_count())),
+            Span::styled("c", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+            Span::raw(": Clusters | "),
+            Span::styled("l", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+            Span::raw(": Log in | "),
+            Span::styled("S", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+            Span::raw(": Snapshots | "),
+            Span::styled("Space", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+            Span::raw(": Mark | "),
+            Span::styled("v", backup_key),
+            Span::raw(": Backup | "),
+            Span::styled("B", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+            Span::raw(": Backups | "),
+            Span::styled("m", migrate_key),
+            Span::raw(": Migrate | "),
+            Span::styled("n", clone_key),
+            Span::raw(": Clone | "),
+            Span::styled("N", create_key),
+            Span::raw(": New CT | "),
+            Span::styled("f", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+            Span::raw(format!(": Show {}", match app.host_filter {
+                HostFilter::Guests => "templates",
+                HostFilter::Templates => "all",
+                HostFilter::All => "guests",
+            })),
         ]),
     ];
 
@@ -152,6 +259,49 @@ fn render_main_view(f: &mut Frame, app: &mut App) {
     f.render_widget(bottom, chunks[2]);
 }
 
+/// Key hint style for an action on `host`, greyed out in read-only mode or when
+/// the privilege is known to be missing
+fn action_key(app: &App, host: Option<&Host>, privilege: &str) -> Style {
+    key_style(!proxmox::is_read_only() && host.is_none_or(|h| app.allows(h, privilege)))
+}
+
+fn key_style(enabled: bool) -> Style {
+    if enabled {
+        Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)
+    } else {
+        Style::default().fg(Color::DarkGray)
+    }
+}
+
+const SPINNER_FRAMES: [&str; 10] = ["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
+
+/// Table title, with a spinner and progress while a background refresh is running
+fn hosts_title(app: &App) -> String {
+    // Guests are the default category, the others are named in the title
+    let hosts = match app.host_filter {
+        HostFilter::Guests => "Hosts".to_string(),
+        filter => format!("Hosts [{}]", filter.label()),
+    };
+    if app.is_loading {
+        format!(
+            "{} {} refreshing {}/{} clusters",
+            hosts,
+            SPINNER_FRAMES[app.loading_frame % SPINNER_FRAMES.len()],
+            app.pending_clusters.len(),
+            app.refresh_total,
+        )
+    } else if app.config.refresh_interval == 0 {
+        hosts
+    } else if app.auto_refresh_paused {
+        format!("{} - auto refresh paused", hosts)
+    } else {
+        match app.auto_refresh_countdown() {
+            Some(secs) => format!("{} - auto refresh in {}s", hosts, secs),
+            None => hosts,
+        }
+    }
+}
+
 fn render_export_view(f: &mut Frame, app: &App) {
     // Create a centered popup
     let area = centered_rect(80, 80, f.area());
@@ -245,12 +395,19 @@ fn render_edit_ip_view(f: &mut Frame, app: &App) {
             Span::styled(": Cancel", Style::default()),
         ])
     );
-    f.render_widget(instructions, chunks[2]);
+    f.render_widget(instructions, chunks[3]);
 }
 
 fn render_setup_view(f: &mut Frame, app: &App) {
+    let fields = app.setup_fields();
+
+    // Bordered 3-line fields when the terminal is tall enough, single lines otherwise
+    let chrome = 2 + 2 + 1 + 3 + 2; // borders, intro, spacer, message, instructions
+    let field_height = if f.area().height >= chrome + 3 * fields.len() as u16 { 3 } else { 1 };
+    let height = chrome + field_height * fields.len() as u16;
+
     // Create a large centered popup for the form
-    let area = centered_rect_fixed(80, 25, f.area());
+    let area = centered_rect_fixed(80, height, f.area());
 
     // Main block
     let block = Block::default()
@@ -261,89 +418,1263 @@ fn render_setup_view(f: &mut Frame, app: &App) {
     let inner_area = block.inner(area);
     f.render_widget(block, area);
 
-    // Split the inner area
+    // Split the inner area: intro, one chunk per field, spacer, message, instructions
+    let mut constraints = vec![Constraint::Length(2)];
+    constraints.extend(fields.iter().map(|_| Constraint::Length(field_height)));
+    constraints.extend([Constraint::Length(1), Constraint::Length(3), Constraint::Length(2)]);
     let chunks = Layout::default()
         .direction(Direction::Vertical)
-        .constraints([
-            Constraint::Length(2),  // Title/instructions
-            Constraint::Length(3),  // Name field
-            Constraint::Length(3),  // Host field
-            Constraint::Length(3),  // Port field
-            Constraint::Length(3),  // Token ID field
-            Constraint::Length(3),  // Token Secret field
-            Constraint::Length(3),  // Verify SSL checkbox
-            Constraint::Length(1),  // Spacer
-            Constraint::Length(4),  // Instructions
-        ])
+        .constraints(constraints)
         .split(inner_area);
 
     // Title/instructions
-    let intro = Paragraph::new("Fill in the details for your Proxmox host. Get API tokens from Datacenter → Permissions → API Tokens")
+    let intro_text = match app.setup_method {
+        SetupMethod::ExistingToken => "Fill in the details for your Proxmox host. Get API tokens from Datacenter → Permissions → API Tokens",
+        SetupMethod::CreateToken => "proxmon logs in once, creates an API token named 'proxmon' and saves it. The password is not stored.",
+    };
+    let intro = Paragraph::new(intro_text)
         .style(Style::default().fg(Color::DarkGray))
         .wrap(Wrap { trim: false });
     f.render_widget(intro, chunks[0]);
 
-    // Helper to render a field
-    let mut render_field = |chunk_idx: usize, label: &str, input: &str, field: SetupField, cursor_pos: usize| {
-        let is_active = app.setup_field == field;
+    for (index, field) in fields.iter().enumerate() {
+        let chunk = chunks[index + 1];
+        let is_active = app.setup_field == *field;
+
+        let (label, value, cursor) = match field {
+            SetupField::Method => ("Method", format!("< {} >", app.setup_method.label()), None),
+            SetupField::Name => ("Name (e.g., vs01)", app.setup_name.value().to_string(), Some(app.setup_name.cursor())),
+            SetupField::Host => ("Host/IP (e.g., 10.1.2.1)", app.setup_host.value().to_string(), Some(app.setup_host.cursor())),
+            SetupField::Port => ("Port (default: 8006)", app.setup_port.value().to_string(), Some(app.setup_port.cursor())),
+            SetupField::Endpoints => ("Failover endpoints (optional, e.g., 10.1.2.2, 10.1.2.3:8006)", app.setup_endpoints.value().to_string(), Some(app.setup_endpoints.cursor())),
+            SetupField::Proxy => ("Proxy (optional, e.g., socks5://127.0.0.1:1080)", app.setup_proxy.value().to_string(), Some(app.setup_proxy.cursor())),
+            SetupField::ApiTokenId => ("API Token ID (e.g., root@pam!mytoken)", app.setup_token_id.value().to_string(), Some(app.setup_token_id.cursor())),
+            SetupField::ApiTokenSecret => ("API Token Secret", app.setup_token_secret.value().to_string(), Some(app.setup_token_secret.cursor())),
+            SetupField::Username => ("Username (e.g., alice)", app.setup_username.value().to_string(), Some(app.setup_username.cursor())),
+            SetupField::Realm => ("Realm (e.g., pam, pve, ldap)", app.setup_realm.value().to_string(), Some(app.setup_realm.cursor())),
+            SetupField::Password => ("Password", "*".repeat(app.setup_password.value().chars().count()), Some(app.setup_password.cursor())),
+            SetupField::Totp => ("TOTP code (only if the account has one)", app.setup_totp.value().to_string(), Some(app.setup_totp.cursor())),
+            SetupField::Role => ("Token permissions", format!("< {} >", app.setup_role.label()), None),
+            SetupField::VerifySsl => {
+                let checkbox_text = if app.setup_verify_ssl { "[X]" } else { "[ ]" };
+                ("", format!("{} Verify SSL (usually off for self-signed certs)", checkbox_text), None)
+            }
+        };
+
         let style = if is_active {
             Style::default().fg(Color::Yellow)
         } else {
             Style::default().fg(Color::DarkGray)
         };
+        let value_style = if is_active && cursor.is_some() {
+            Style::default().fg(Color::White)
+        } else {
+            style
+        };
 
-        let field_widget = Paragraph::new(input)
-            .style(if is_active { Style::default().fg(Color::White) } else { Style::default().fg(Color::DarkGray) })
-            .block(Block::default().borders(Borders::ALL).title(label).style(style));
-
-        f.render_widget(field_widget, chunks[chunk_idx]);
+        let offset = if field_height == 3 {
+            let field_widget = Paragraph::new(value)
+                .style(value_style)
+                .block(Block::default().borders(Borders::ALL).title(label).style(style));
+            f.render_widget(field_widget, chunk);
+            (1, 1)
+        } else {
+            let prefix = if label.is_empty() { String::new() } else { format!("{}: ", label) };
+            let width = prefix.chars().count() as u16;
+            let line = Line::from(vec![Span::styled(prefix, style), Span::styled(value, value_style)]);
+            f.render_widget(Paragraph::new(line), chunk);
+            (width, 0)
+        };
 
         // Show cursor if active
-        if is_active && field != SetupField::VerifySsl {
+        if let (true, Some(cursor_pos)) = (is_active, cursor) {
+            f.set_cursor_position((chunk.x + offset.0 + cursor_pos as u16, chunk.y + offset.1));
+        }
+    }
+
+    let message_index = fields.len() + 2;
+    if let Some(message) = &app.setup_message {
+        let style = if app.setup_busy || app.setup_offered_fingerprint.is_some() {
+            Style::default().fg(Color::Yellow)
+        } else {
+            Style::default().fg(Color::Red)
+        };
+        let message = Paragraph::new(message.as_str()).style(style).wrap(Wrap { trim: false });
+        f.render_widget(message, chunks[message_index]);
+    }
+
+    // Instructions
+    let instructions = if app.setup_offered_fingerprint.is_some() {
+        Paragraph::new(Line::from(vec![
+            Span::styled("Enter", Style::default().fg(Color::Green).add_modifier(Modifier::BOLD)),
+            Span::styled(": Trust this certificate | ", Style::default()),
+            Span::styled("Esc", Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)),
+            Span::styled(": Don't trust it", Style::default()),
+        ]))
+    } else if app.setup_pending_host.is_some() {
+        Paragraph::new(Line::from(vec![
+            Span::styled("Enter", Style::default().fg(Color::Green).add_modifier(Modifier::BOLD)),
+            Span::styled(": Save anyway | ", Style::default()),
+            Span::styled("Esc", Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)),
+            Span::styled(": Back to the failing field", Style::default()),
+        ]))
+    } else {
+        Paragraph::new(vec![
+            Line::from(vec![
+                Span::styled("Tab/Shift+Tab", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+                Span::styled(": Navigate fields | ", Style::default()),
+                Span::styled("Space", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+                Span::styled(": Change method, permissions or SSL checkbox", Style::default()),
+            ]),
+            Line::from(vec![
+                Span::styled("Enter", Style::default().fg(Color::Green).add_modifier(Modifier::BOLD)),
+                Span::styled(" on last field: Check & save | ", Style::default()),
+                Span::styled("Esc", Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)),
+                Span::styled(": Cancel", Style::default()),
+            ]),
+        ])
+    };
+    f.render_widget(instructions, chunks[message_index + 1]);
+}
+
+fn render_login_view(f: &mut Frame, app: &App) {
+    let area = centered_rect_fixed(60, 11, f.area());
+
+    let (entry, credential) = app
+        .login_queue
+        .first()
+        .cloned()
+        .unwrap_or((String::new(), Credential::Viewer));
+    let pve_host = app.config.proxmox_hosts.iter().find(|h| h.name == entry);
+    let (user, title) = match credential {
+        Credential::Viewer => (pve_host.map(|h| h.user_at_realm()), format!(" Log in to {} ", entry)),
+        Credential::Operator => (
+            pve_host.map(|h| h.operator_user_at_realm()),
+            format!(" Operator login for {} ", entry),
+        ),
+    };
+
+    let block = Block::default()
+        .title(title)
+        .borders(Borders::ALL)
+        .style(Style::default().bg(Color::Black).fg(Color::Cyan));
+
+    let inner_area = block.inner(area);
+    f.render_widget(block, area);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(1),  // User
+            Constraint::Length(3),  // Password or TOTP field
+            Constraint::Length(2),  // Error / progress
+            Constraint::Length(1),  // Instructions
+        ])
+        .split(inner_area);
+
+    f.render_widget(
+        Paragraph::new(format!("User: {}", user.unwrap_or_default())).style(Style::default().fg(Color::White)),
+        chunks[0],
+    );
+
+    // Never echo the password, only show how many characters were typed
+    let (label, text, cursor) = match app.login_challenge {
+        Some(_) => ("TOTP code", app.login_totp.value().to_string(), app.login_totp.cursor()),
+        None => ("Password", "*".repeat(app.login_password.value().chars().count()), app.login_password.cursor()),
+    };
+    let field = Paragraph::new(text)
+        .style(Style::default().fg(Color::White))
+        .block(Block::default().borders(Borders::ALL).title(label).style(Style::default().fg(Color::Yellow)));
+    f.render_widget(field, chunks[1]);
+    f.set_cursor_position((chunks[1].x + cursor as u16 + 1, chunks[1].y + 1));
+
+    let (message, style) = if app.login_in_progress {
+        ("Logging in...".to_string(), Style::default().fg(Color::Yellow))
+    } else if let Some(error) = &app.login_error {
+        (error.clone(), Style::default().fg(Color::Red))
+    } else {
+        (String::new(), Style::default())
+    };
+    f.render_widget(Paragraph::new(message).style(style).wrap(Wrap { trim: false }), chunks[2]);
+
+    let instructions = Paragraph::new(
+        Line::from(vec![
+            Span::styled("Enter", Style::default().fg(Color::Green).add_modifier(Modifier::BOLD)),
+            Span::styled(": Log in | ", Style::default()),
+            Span::styled("Esc", Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)),
+            Span::styled(
+                if credential == Credential::Operator { ": Cancel the action" } else { ": Skip this host" },
+                Style::default(),
+            ),
+        ])
+    );
+    f.render_widget(instructions, chunks[3]);
+}
+
+fn render_confirm_action_view(f: &mut Frame, app: &App) {
+    // Protected guests get a field to type the name into
+    let height = if app.confirm_name.is_some() { 11 } else { 7 };
+    let area = centered_rect_fixed(50, height, f.area());
+
+    let action = app.pending_action.as_ref().map(|a| a.label()).unwrap_or_default();
+
+    let block = Block::default()
+        .title(format!(" {} ", action))
+        .borders(Borders::ALL)
+        .style(Style::default().bg(Color::Black).fg(Color::Cyan));
+
+    let inner_area = block.inner(area);
+    f.render_widget(block, area);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3),  // Question
+            Constraint::Length(if app.confirm_name.is_some() { 4 } else { 0 }),  // Name to type
+            Constraint::Length(1),  // Instructions
+        ])
+        .split(inner_area);
+
+    let targets: Vec<&proxmox::Host> = app.action_targets.iter().filter_map(|id| app.host_by_id(id)).collect();
+    let target = match targets.as_slice() {
+        [] => String::new(),
+        [host] => format!(
+            "{} {} (vmid {} on {})?",
+            action,
+            host.name,
+            host.vmid.map(|id| id.to_string()).unwrap_or_default(),
+            host.node.as_deref().unwrap_or("-"),
+        ),
+        hosts => format!(
+            "{} for {} guests: {}?",
+            action,
+            hosts.len(),
+            hosts.iter().map(|h| h.name.as_str()).collect::<Vec<_>>().join(", "),
+        ),
+    };
+
+    let question = Paragraph::new(target)
+        .style(Style::default().fg(Color::White))
+        .wrap(Wrap { trim: false });
+    f.render_widget(question, chunks[0]);
+
+    let (confirm_keys, cancel_keys) = match &app.confirm_name {
+        Some(name) => {
+            let typed = app.confirm_input.value();
+            let field_area = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Length(1), Constraint::Length(3)])
+                .split(chunks[1]);
+
+            f.render_widget(
+                Paragraph::new(format!("{} is protected, type its name to confirm:", name))
+                    .style(Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)),
+                field_area[0],
+            );
+            let field_style = if typed == name {
+                Style::default().fg(Color::Green)
+            } else {
+                Style::default().fg(Color::Yellow)
+            };
+            f.render_widget(
+                Paragraph::new(typed)
+                    .style(Style::default().fg(Color::White))
+                    .block(Block::default().borders(Borders::ALL).style(field_style)),
+                field_area[1],
+            );
             f.set_cursor_position((
-                chunks[chunk_idx].x + cursor_pos as u16 + 1,
-                chunks[chunk_idx].y + 1,
+                field_area[1].x + app.confirm_input.cursor() as u16 + 1,
+                field_area[1].y + 1,
             ));
+            ("Enter", "Esc")
         }
+        None => ("y/Enter", "n/Esc"),
     };
 
-    // Render fields
-    render_field(1, "Name (e.g., vs01)", app.setup_name.value(), SetupField::Name, app.setup_name.cursor());
-    render_field(2, "Host/IP (e.g., 10.1.2.1)", app.setup_host.value(), SetupField::Host, app.setup_host.cursor());
-    render_field(3, "Port (default: 8006)", app.setup_port.value(), SetupField::Port, app.setup_port.cursor());
-    render_field(4, "API Token ID (e.g., root@pam!mytoken)", app.setup_token_id.value(), SetupField::ApiTokenId, app.setup_token_id.cursor());
-    render_field(5, "API Token Secret", app.setup_token_secret.value(), SetupField::ApiTokenSecret, app.setup_token_secret.cursor());
-
-    // Verify SSL checkbox
-    let is_ssl_active = app.setup_field == SetupField::VerifySsl;
-    let checkbox_text = if app.setup_verify_ssl { "[X]" } else { "[ ]" };
-    let checkbox = Paragraph::new(format!("{} Verify SSL (usually off for self-signed certs)", checkbox_text))
-        .style(if is_ssl_active {
-            Style::default().fg(Color::Yellow)
+    let instructions = Paragraph::new(
+        Line::from(vec![
+            Span::styled(confirm_keys, Style::default().fg(Color::Green).add_modifier(Modifier::BOLD)),
+            Span::styled(": Confirm | ", Style::default()),
+            Span::styled(cancel_keys, Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)),
+            Span::styled(": Cancel", Style::default()),
+        ])
+    );
+    f.render_widget(instructions, chunks[3]);
+}
+
+fn render_clusters_view(f: &mut Frame, app: &App) {
+    let area = centered_rect(80, 60, f.area());
+
+    let block = Block::default()
+        .title(" Clusters ")
+        .borders(Borders::ALL)
+        .style(Style::default().bg(Color::Black));
+
+    let inner_area = block.inner(area);
+    f.render_widget(block, area);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Min(0),     // Cluster table
+            Constraint::Length(1),  // Instructions
+        ])
+        .split(inner_area);
+
+    let header_cells = ["Cluster", "Status", "Guests", "Details"]
+        .iter()
+        .map(|h| Cell::from(*h).style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)));
+    let header = Row::new(header_cells).height(1).bottom_margin(1);
+
+    let rows = app.clusters.iter().map(|cluster| {
+        let status_style = if cluster.status == ClusterStatus::Ok {
+            Style::default().fg(Color::Green)
         } else {
-            Style::default().fg(Color::DarkGray)
-        })
-        .block(Block::default().borders(Borders::ALL).style(
-            if is_ssl_active { Style::default().fg(Color::Yellow) } else { Style::default().fg(Color::DarkGray) }
-        ));
-    f.render_widget(checkbox, chunks[6]);
+            Style::default().fg(Color::Red)
+        };
+        let name = if cluster.members.len() > 1 {
+            format!("{} (+{})", cluster.name, cluster.members[1..].join(", "))
+        } else {
+            cluster.name.clone()
+        };
+        let details = match (&cluster.message, &cluster.endpoint) {
+            (Some(message), _) => message.clone(),
+            (None, Some(endpoint)) if cluster.failed_over => format!("via {} (failover)", endpoint),
+            (None, Some(endpoint)) => format!("via {}", endpoint),
+            (None, None) => String::new(),
+        };
+        Row::new(vec![
+            Cell::from(name),
+            Cell::from(cluster.status.as_str().to_string()).style(status_style),
+            Cell::from(cluster.host_count.to_string()),
+            Cell::from(details),
+        ])
+        .height(1)
+    });
+
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Percentage(20),
+            Constraint::Percentage(15),
+            Constraint::Percentage(10),
+            Constraint::Percentage(55),
+        ],
+    )
+    .header(header);
+    f.render_widget(table, chunks[0]);
+
+    let instructions = Paragraph::new(Line::from(vec![
+        Span::styled("Enter/Esc/q", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+        Span::styled(": Close", Style::default()),
+    ]));
+    f.render_widget(instructions, chunks[1]);
+}
+
+fn render_snapshots_view(f: &mut Frame, app: &App) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Min(0),     // Snapshot tree
+            Constraint::Length(4),  // Status and keybindings
+        ])
+        .split(f.area());
+
+    let title = match app.snapshot_target() {
+        Some(host) => format!(
+            " Snapshots of {} (vmid {}) ",
+            host.name,
+            host.vmid.map(|id| id.to_string()).unwrap_or_default()
+        ),
+        None => " Snapshots ".to_string(),
+    };
+
+    let header_cells = ["Name", "Taken", "RAM", "Description"]
+        .iter()
+        .map(|h| Cell::from(*h).style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)));
+    let header = Row::new(header_cells).height(1).bottom_margin(1);
+
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let rows = app.snapshots.iter().map(|(depth, snapshot)| {
+        let indent = if *depth == 0 { String::new() } else { format!("{}└─ ", "   ".repeat(depth - 1)) };
+        if snapshot.is_current() {
+            return Row::new(vec![
+                Cell::from(format!("{}NOW", indent)).style(Style::default().fg(Color::Green)),
+                Cell::from(""),
+                Cell::from(""),
+                Cell::from("Current state").style(Style::default().fg(Color::DarkGray)),
+            ]);
+        }
+        Row::new(vec![
+            Cell::from(format!("{}{}", indent, snapshot.name)),
+            Cell::from(snapshot.snaptime.map(|t| format_age(now.saturating_sub(t))).unwrap_or_default()),
+            Cell::from(if snapshot.vmstate != 0 { "yes" } else { "" }),
+            // Multi-line descriptions are shown by their first line
+            Cell::from(snapshot.description.lines().next().unwrap_or_default().to_string()),
+        ])
+    });
+
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Percentage(35),
+            Constraint::Percentage(12),
+            Constraint::Percentage(6),
+            Constraint::Percentage(47),
+        ],
+    )
+    .header(header)
+    .block(Block::default().borders(Borders::ALL).title(title))
+    .row_highlight_style(
+        Style::default()
+            .bg(Color::DarkGray)
+            .add_modifier(Modifier::BOLD),
+    )
+    .highlight_symbol(">> ");
+
+    let mut table_state = TableState::default();
+    if !app.snapshots.is_empty() {
+        table_state.select(Some(app.snapshot_selected));
+    }
+    f.render_stateful_widget(table, chunks[0], &mut table_state);
+
+    let (status, status_style) = if app.snapshots_loading {
+        ("Loading snapshots...".to_string(), Style::default().fg(Color::Yellow))
+    } else if let Some(error) = &app.snapshot_error {
+        (error.clone(), Style::default().fg(Color::Red))
+    } else if let Some(message) = &app.last_error {
+        (message.clone(), Style::default().fg(Color::Yellow))
+    } else {
+        (String::new(), Style::default())
+    };
 
-    // Instructions
     let instructions = Paragraph::new(vec![
+        Line::from(Span::styled(status, status_style)),
         Line::from(vec![
-            Span::styled("Tab/Shift+Tab", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
-            Span::styled(": Navigate fields | ", Style::default()),
-            Span::styled("Space", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
-            Span::styled(": Toggle SSL checkbox", Style::default()),
+            Span::styled("↑/↓", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+            Span::raw(": Nav | "),
+            Span::styled("n", action_key(app, app.snapshot_target(), permissions::VM_SNAPSHOT)),
+            Span::raw(": New snapshot | "),
+            Span::styled("R", action_key(app, app.snapshot_target(), permissions::VM_SNAPSHOT_ROLLBACK)),
+            Span::raw(": Rollback | "),
+            Span::styled("d", action_key(app, app.snapshot_target(), permissions::VM_SNAPSHOT)),
+            Span::raw(": Delete | "),
+            Span::styled("r", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+            Span::raw(": Reload | "),
+            Span::styled("Esc/q", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+            Span::raw(": Back"),
         ]),
+    ])
+    .block(Block::default().borders(Borders::ALL));
+    f.render_widget(instructions, chunks[1]);
+}
+
+fn render_snapshot_create_view(f: &mut Frame, app: &App) {
+    let area = centered_rect_fixed(60, 11, f.area());
+    let is_vm = app.snapshot_target().is_some_and(|h| h.host_type == HostType::VM);
+
+    let block = Block::default()
+        .title(format!(
+            " New snapshot of {} ",
+            app.snapshot_target().map(|h| h.name.as_str()).unwrap_or_default()
+        ))
+        .borders(Borders::ALL)
+        .style(Style::default().bg(Color::Black).fg(Color::Cyan));
+
+    let inner_area = block.inner(area);
+    f.render_widget(block, area);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3),  // Name
+            Constraint::Length(3),  // Description
+            Constraint::Length(2),  // RAM toggle
+            Constraint::Length(1),  // Instructions
+        ])
+        .split(inner_area);
+
+    let field_style = |field: SnapshotField| {
+        if app.snapshot_field == field {
+            Style::default().fg(Color::Yellow)
+        } else {
+            Style::default().fg(Color::DarkGray)
+        }
+    };
+
+    for (field, label, input, area) in [
+        (SnapshotField::Name, "Name", &app.snapshot_name, chunks[0]),
+        (SnapshotField::Description, "Description (optional)", &app.snapshot_description, chunks[1]),
+    ] {
+        f.render_widget(
+            Paragraph::new(input.value())
+                .style(Style::default().fg(Color::White))
+                .block(Block::default().borders(Borders::ALL).title(label).style(field_style(field))),
+            area,
+        );
+        if app.snapshot_field == field {
+            f.set_cursor_position((area.x + input.cursor() as u16 + 1, area.y + 1));
+        }
+    }
+
+    let vmstate = if !is_vm {
+        "Include RAM: not available for containers".to_string()
+    } else {
+        format!("[{}] Include RAM (Space to toggle)", if app.snapshot_vmstate { "x" } else { " " })
+    };
+    f.render_widget(Paragraph::new(vmstate).style(field_style(SnapshotField::Vmstate)), chunks[2]);
+
+    let instructions = Paragraph::new(
         Line::from(vec![
             Span::styled("Enter", Style::default().fg(Color::Green).add_modifier(Modifier::BOLD)),
-            Span::styled(" on last field: Save | ", Style::default()),
+            Span::styled(": Create | ", Style::default()),
+            Span::styled("Tab", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+            Span::styled(": Next field | ", Style::default()),
             Span::styled("Esc", Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)),
             Span::styled(": Cancel", Style::default()),
-        ]),
-    ]);
-    f.render_widget(instructions, chunks[8]);
+        ])
+    );
+    f.render_widget(instructions, chunks[3]);
+}
+
+fn render_backups_view(f: &mut Frame, app: &App) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Min(0),     // Backup list
+            Constraint::Length(4),  // Status and keybindings
+        ])
+        .split(f.area());
+
+    let title = match app.backup_target() {
+        Some(host) => format!(
+            " Backups of {} (vmid {}) ",
+            host.name,
+            host.vmid.map(|id| id.to_string()).unwrap_or_default()
+        ),
+        None => " Backups ".to_string(),
+    };
+
+    let header_cells = ["Storage", "Archive", "Size", "Age", "Notes"]
+        .iter()
+        .map(|h| Cell::from(*h).style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)));
+    let header = Row::new(header_cells).height(1).bottom_margin(1);
+
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let rows = app.backups.iter().map(|backup| {
+        Row::new(vec![
+            Cell::from(backup.storage().to_string()),
+            Cell::from(backup.file_name().to_string()),
+            Cell::from(format_bytes(backup.size)),
+            Cell::from(format_age(now.saturating_sub(backup.ctime))),
+            Cell::from(backup.notes.lines().next().unwrap_or_default().to_string()),
+        ])
+    });
+
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Percentage(12),
+            Constraint::Percentage(43),
+            Constraint::Percentage(10),
+            Constraint::Percentage(10),
+            Constraint::Percentage(25),
+        ],
+    )
+    .header(header)
+    .block(Block::default().borders(Borders::ALL).title(title))
+    .row_highlight_style(
+        Style::default()
+            .bg(Color::DarkGray)
+            .add_modifier(Modifier::BOLD),
+    )
+    .highlight_symbol(">> ");
+
+    let mut table_state = TableState::default();
+    if !app.backups.is_empty() {
+        table_state.select(Some(app.backup_selected));
+    }
+    f.render_stateful_widget(table, chunks[0], &mut table_state);
+
+    let (status, status_style) = if app.backups_loading {
+        ("Loading backups...".to_string(), Style::default().fg(Color::Yellow))
+    } else if let Some(error) = &app.backup_error {
+        (error.clone(), Style::default().fg(Color::Red))
+    } else if let Some(message) = &app.last_error {
+        (message.clone(), Style::default().fg(Color::Yellow))
+    } else if app.backups.is_empty() {
+        ("No backups found".to_string(), Style::default().fg(Color::DarkGray))
+    } else {
+        (String::new(), Style::default())
+    };
+
+    let instructions = Paragraph::new(vec![
+        Line::from(Span::styled(status, status_style)),
+        Line::from(vec![
+            Span::styled("↑/↓", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+            Span::raw(": Nav | "),
+            Span::styled("n", action_key(app, app.backup_target(), permissions::VM_BACKUP)),
+            Span::raw(": Back up now | "),
+            Span::styled("Enter/R", action_key(app, app.backup_target(), permissions::VM_ALLOCATE)),
+            Span::raw(": Restore as new guest | "),
+            Span::styled("r", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+            Span::raw(": Reload | "),
+            Span::styled("Esc/q", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+            Span::raw(": Back"),
+        ]),
+    ])
+    .block(Block::default().borders(Borders::ALL));
+    f.render_widget(instructions, chunks[1]);
+}
+
+fn render_backup_create_view(f: &mut Frame, app: &App) {
+    let area = centered_rect_fixed(60, 9, f.area());
+
+    let target = match app.backup_targets.as_slice() {
+        [id] => app.host_by_id(id).map(|h| h.name.clone()).unwrap_or_default(),
+        ids => format!("{} guests", ids.len()),
+    };
+    let block = Block::default()
+        .title(format!(" Back up {} ", target))
+        .borders(Borders::ALL)
+        .style(Style::default().bg(Color::Black).fg(Color::Cyan));
+
+    let inner_area = block.inner(area);
+    f.render_widget(block, area);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(1),  // Storage
+            Constraint::Length(1),  // Mode
+            Constraint::Length(2),  // Compression
+            Constraint::Length(1),  // Status
+            Constraint::Length(1),  // Instructions
+        ])
+        .split(inner_area);
+
+    let storage = match app.backup_storages.get(app.backup_storage_index) {
+        Some(storage) => match storage.avail {
+            Some(avail) => format!("{} ({} free)", storage.storage, format_bytes(avail)),
+            None => storage.storage.clone(),
+        },
+        None => "loading...".to_string(),
+    };
+
+    for (field, label, value, area) in [
+        (BackupField::Storage, "Storage", storage, chunks[0]),
+        (BackupField::Mode, "Mode", app.backup_mode.as_str().to_string(), chunks[1]),
+        (BackupField::Compression, "Compression", app.backup_compress.label().to_string(), chunks[2]),
+    ] {
+        let style = if app.backup_field == field {
+            Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(Color::White)
+        };
+        f.render_widget(
+            Paragraph::new(Line::from(vec![
+                Span::styled(format!("{:<13}", label), Style::default().fg(Color::Cyan)),
+                Span::styled(format!("< {} >", value), style),
+            ])),
+            area,
+        );
+    }
+
+    if let Some(message) = &app.last_error {
+        f.render_widget(
+            Paragraph::new(message.as_str()).style(Style::default().fg(Color::Red)),
+            chunks[3],
+        );
+    }
+
+    let instructions = Paragraph::new(
+        Line::from(vec![
+            Span::styled("Enter", Style::default().fg(Color::Green).add_modifier(Modifier::BOLD)),
+            Span::styled(": Start | ", Style::default()),
+            Span::styled("Tab", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+            Span::styled(": Next field | ", Style::default()),
+            Span::styled("Space", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+            Span::styled(": Change | ", Style::default()),
+            Span::styled("Esc", Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)),
+            Span::styled(": Cancel", Style::default()),
+        ])
+    );
+    f.render_widget(instructions, chunks[4]);
+}
+
+fn render_restore_view(f: &mut Frame, app: &App) {
+    let area = centered_rect_fixed(60, 9, f.area());
+
+    let block = Block::default()
+        .title(" Restore backup as a new guest ")
+        .borders(Borders::ALL)
+        .style(Style::default().bg(Color::Black).fg(Color::Cyan));
+
+    let inner_area = block.inner(area);
+    f.render_widget(block, area);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(2),  // Archive
+            Constraint::Length(3),  // New vmid
+            Constraint::Length(1),  // Status
+            Constraint::Length(1),  // Instructions
+        ])
+        .split(inner_area);
+
+    f.render_widget(
+        Paragraph::new(app.restore_archive.as_deref().unwrap_or_default())
+            .style(Style::default().fg(Color::White))
+            .wrap(Wrap { trim: false }),
+        chunks[0],
+    );
+
+    f.render_widget(
+        Paragraph::new(app.restore_vmid.value())
+            .style(Style::default().fg(Color::White))
+            .block(Block::default().borders(Borders::ALL).title("New vmid").style(Style::default().fg(Color::Yellow))),
+        chunks[1],
+    );
+    f.set_cursor_position((chunks[1].x + app.restore_vmid.cursor() as u16 + 1, chunks[1].y + 1));
+
+    if let Some(message) = &app.last_error {
+        f.render_widget(
+            Paragraph::new(message.as_str()).style(Style::default().fg(Color::Red)),
+            chunks[2],
+        );
+    }
+
+    let instructions = Paragraph::new(
+        Line::from(vec![
+            Span::styled("Enter", Style::default().fg(Color::Green).add_modifier(Modifier::BOLD)),
+            Span::styled(": Restore | ", Style::default()),
+            Span::styled("Esc", Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)),
+            Span::styled(": Cancel", Style::default()),
+        ])
+    );
+    f.render_widget(instructions, chunks[3]);
+}
+
+fn render_migrate_view(f: &mut Frame, app: &App) {
+    let area = centered_rect(60, 50, f.area());
+    let Some(host) = app.migrate_target() else {
+        return;
+    };
+
+    let block = Block::default()
+        .title(format!(
+            " Migrate {} from {} ",
+            host.name,
+            host.node.as_deref().unwrap_or("-")
+        ))
+        .borders(Borders::ALL)
+        .style(Style::default().bg(Color::Black).fg(Color::Cyan));
+
+    let inner_area = block.inner(area);
+    f.render_widget(block, area);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(2),  // How the guest will be moved
+            Constraint::Min(0),     // Target nodes
+            Constraint::Length(1),  // Instructions
+        ])
+        .split(inner_area);
+
+    let mode = match (host.host_type == HostType::VM, host.status == "running") {
+        (true, true) => "Running: live migration, local disks are copied along",
+        (false, true) => "Running: the container is restarted on the target",
+        (_, false) => "Stopped: offline migration",
+    };
+    f.render_widget(Paragraph::new(mode).style(Style::default().fg(Color::White)), chunks[0]);
+
+    if app.migrate_loading || app.migrate_error.is_some() {
+        let (text, style) = match &app.migrate_error {
+            Some(error) => (error.as_str(), Style::default().fg(Color::Red)),
+            None => ("Loading nodes...", Style::default().fg(Color::Yellow)),
+        };
+        f.render_widget(Paragraph::new(text).style(style).wrap(Wrap { trim: false }), chunks[1]);
+    } else {
+        let header_cells = ["Node", "Free memory"]
+            .iter()
+            .map(|h| Cell::from(*h).style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)));
+        let header = Row::new(header_cells).height(1);
+
+        let rows = app.migrate_nodes.iter().map(|node| {
+            // Nodes without room for the guest's configured memory are shown in red
+            let fits = match (node.free_mem(), host.maxmem) {
+                (Some(free), Some(needed)) => free >= needed,
+                _ => true,
+            };
+            Row::new(vec![
+                Cell::from(node.node.clone()),
+                Cell::from(node.free_mem().map(format_bytes).unwrap_or_else(|| "-".to_string())),
+            ])
+            .style(if fits { Style::default().fg(Color::White) } else { Style::default().fg(Color::Red) })
+        });
+
+        let table = Table::new(rows, [Constraint::Percentage(50), Constraint::Percentage(50)])
+            .header(header)
+            .row_highlight_style(
+                Style::default()
+                    .bg(Color::DarkGray)
+                    .add_modifier(Modifier::BOLD),
+            )
+            .highlight_symbol(">> ");
+
+        let mut table_state = TableState::default();
+        table_state.select(Some(app.migrate_selected));
+        f.render_stateful_widget(table, chunks[1], &mut table_state);
+    }
+
+    let needs = host
+        .maxmem
+        .map(|m| format!(" | Guest memory: {}", format_bytes(m)))
+        .unwrap_or_default();
+    let instructions = Paragraph::new(
+        Line::from(vec![
+            Span::styled("Enter", Style::default().fg(Color::Green).add_modifier(Modifier::BOLD)),
+            Span::styled(": Migrate | ", Style::default()),
+            Span::styled("Esc", Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)),
+            Span::styled(": Cancel", Style::default()),
+            Span::styled(needs, Style::default().fg(Color::DarkGray)),
+        ])
+    );
+    f.render_widget(instructions, chunks[2]);
+}
+
+fn render_clone_view(f: &mut Frame, app: &App) {
+    let fields = app.clone_fields();
+    // One line per field, plus status and instructions
+    let area = centered_rect_fixed(70, fields.len() as u16 + 5, f.area());
+    let Some(source) = app.clone_target() else {
+        return;
+    };
+
+    let block = Block::default()
+        .title(format!(
+            " Clone {} {} ",
+            if source.template { "template" } else { "guest" },
+            source.name
+        ))
+        .borders(Borders::ALL)
+        .style(Style::default().bg(Color::Black).fg(Color::Cyan));
+
+    let inner_area = block.inner(area);
+    f.render_widget(block, area);
+
+    let mut constraints = vec![Constraint::Length(1); fields.len()];
+    constraints.extend([Constraint::Length(1), Constraint::Length(1), Constraint::Length(1)]);
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(constraints)
+        .split(inner_area);
+
+    const LABEL_WIDTH: usize = 16;
+    for (field, area) in fields.iter().zip(chunks.iter()) {
+        let (label, value, hint) = match field {
+            CloneField::Node => (
+                "Target node",
+                format!("< {} >", app.clone_nodes.get(app.clone_node_index).map(String::as_str).unwrap_or("-")),
+                "",
+            ),
+            CloneField::Vmid => ("VMID", app.clone_vmid.value().to_string(), ""),
+            CloneField::Name => ("Name", app.clone_name.value().to_string(), "empty: chosen by Proxmox"),
+            CloneField::Mode => (
+                "Mode",
+                format!("< {} >", if app.clone_full { "full clone" } else { "linked clone" }),
+                "",
+            ),
+            CloneField::Storage => (
+                "Target storage",
+                app.clone_storage.value().to_string(),
+                if app.clone_full { "empty: same as source" } else { "full clones only" },
+            ),
+            CloneField::Pool => ("Pool", app.clone_pool.value().to_string(), "optional"),
+            CloneField::CiUser => ("Cloud-init user", app.clone_ciuser.value().to_string(), "optional"),
+            CloneField::SshKeys => ("SSH key", app.clone_sshkeys.value().to_string(), "optional"),
+            CloneField::IpConfig => ("ipconfig0", app.clone_ipconfig.value().to_string(), "e.g. ip=dhcp or ip=10.0.0.5/24,gw=10.0.0.1"),
+            CloneField::Start => ("Start", format!("[{}] after cloning", if app.clone_start { "x" } else { " " }), ""),
+        };
+
+        let active = app.clone_field == *field;
+        let value_style = if active {
+            Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(Color::White)
+        };
+        let mut spans = vec![
+            Span::styled(format!("{:<width$}", label, width = LABEL_WIDTH), Style::default().fg(Color::Cyan)),
+            Span::styled(value.clone(), value_style),
+        ];
+        if value.is_empty() && !hint.is_empty() {
+            spans.push(Span::styled(hint, Style::default().fg(Color::DarkGray)));
+        }
+        f.render_widget(Paragraph::new(Line::from(spans)), *area);
+
+        if active {
+            let cursor = match field {
+                CloneField::Vmid => Some(&app.clone_vmid),
+                CloneField::Name => Some(&app.clone_name),
+                CloneField::Storage => Some(&app.clone_storage),
+                CloneField::Pool => Some(&app.clone_pool),
+                CloneField::CiUser => Some(&app.clone_ciuser),
+                CloneField::SshKeys => Some(&app.clone_sshkeys),
+                CloneField::IpConfig => Some(&app.clone_ipconfig),
+                CloneField::Node | CloneField::Mode | CloneField::Start => None,
+            };
+            if let Some(input) = cursor {
+                f.set_cursor_position((area.x + (LABEL_WIDTH + input.visual_cursor()) as u16, area.y));
+            }
+        }
+    }
+
+    if let Some(message) = &app.last_error {
+        f.render_widget(
+            Paragraph::new(message.as_str()).style(Style::default().fg(Color::Red)),
+            chunks[fields.len() + 1],
+        );
+    }
+
+    let instructions = Paragraph::new(
+        Line::from(vec![
+            Span::styled("Enter", Style::default().fg(Color::Green).add_modifier(Modifier::BOLD)),
+            Span::styled(": Clone | ", Style::default()),
+            Span::styled("Tab/↑/↓", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+            Span::styled(": Field | ", Style::default()),
+            Span::styled("Space", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+            Span::styled(": Change choice | ", Style::default()),
+            Span::styled("Esc", Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)),
+            Span::styled(": Cancel", Style::default()),
+        ])
+    );
+    f.render_widget(instructions, chunks[fields.len() + 2]);
+}
+
+fn render_container_view(f: &mut Frame, app: &App) {
+    let fields = app.container_fields();
+    const LABEL_WIDTH: usize = 16;
+    let label = |text: &str| Span::styled(format!("{:<width$}", text, width = LABEL_WIDTH), Style::default().fg(Color::Cyan));
+
+    // The review step lists what is about to be created above its fields
+    let mut summary: Vec<Line> = Vec::new();
+    if app.container_step == ContainerStep::Review {
+        if let Ok(spec) = app.container_spec() {
+            let network = match &spec.network {
+                Network::Dhcp => format!("{}, DHCP", spec.bridge),
+                Network::Static { cidr, gateway } => match gateway {
+                    Some(gateway) => format!("{}, {} via {}", spec.bridge, cidr, gateway),
+                    None => format!("{}, {}", spec.bridge, cidr),
+                },
+            };
+            let value = |text: String| Span::styled(text, Style::default().fg(Color::White));
+            summary = vec![
+                Line::from(vec![label("Node"), value(app.container_node().unwrap_or("-").to_string())]),
+                Line::from(vec![label("Template"), value(containers::template_name(&spec.ostemplate).to_string())]),
+                Line::from(vec![label("Container"), value(format!("{} ({})", spec.hostname, spec.vmid))]),
+                Line::from(vec![
+                    label("Resources"),
+                    value(format!(
+                        "{} cores, {} MiB, {} GiB on {}",
+                        spec.cores, spec.memory_mb, spec.rootfs_gb, spec.rootfs_storage
+                    )),
+                ]),
+                Line::from(vec![label("Network"), value(network)]),
+                Line::from(vec![label("SSH key"), value(if spec.ssh_key.is_some() { "yes" } else { "none" }.to_string())]),
+                Line::from(""),
+            ];
+        }
+    }
+
+    // Summary, one line per field, plus status and instructions
+    let area = centered_rect_fixed(70, (summary.len() + fields.len()) as u16 + 5, f.area());
+    let block = Block::default()
+        .title(format!(
+            " New container in {} - step {}/{}: {} ",
+            app.container_cluster.as_deref().unwrap_or("-"),
+            app.container_step.number(),
+            ContainerStep::COUNT,
+            app.container_step.title()
+        ))
+        .borders(Borders::ALL)
+        .style(Style::default().bg(Color::Black).fg(Color::Cyan));
+
+    let inner_area = block.inner(area);
+    f.render_widget(block, area);
+
+    let mut constraints = vec![Constraint::Length(1); summary.len() + fields.len()];
+    constraints.extend([Constraint::Length(1), Constraint::Length(1), Constraint::Length(1)]);
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(constraints)
+        .split(inner_area);
+
+    for (line, area) in summary.iter().zip(chunks.iter()) {
+        f.render_widget(Paragraph::new(line.clone()), *area);
+    }
+
+    let choice = |value: Option<&str>| format!("< {} >", value.unwrap_or("-"));
+    let check = |checked: bool| if checked { "x" } else { " " };
+    let loading = app.container_options_loading;
+    for (field, area) in fields.iter().zip(chunks.iter().skip(summary.len())) {
+        let (name, value, hint) = match field {
+            ContainerField::Cluster => ("Cluster", choice(app.container_cluster.as_deref()), ""),
+            ContainerField::Node => (
+                "Node",
+                if app.container_nodes.is_empty() { String::new() } else { choice(app.container_node()) },
+                "loading...",
+            ),
+            ContainerField::Template => (
+                "OS template",
+                match app.container_options.templates.get(app.container_template_index) {
+                    Some(template) => choice(Some(containers::template_name(template))),
+                    None => String::new(),
+                },
+                if loading { "loading..." } else { "none on this node" },
+            ),
+            ContainerField::Hostname => ("Hostname", app.container_hostname.value().to_string(), ""),
+            ContainerField::Vmid => ("VMID", app.container_vmid.value().to_string(), ""),
+            ContainerField::SshKey => ("SSH public key", app.container_ssh_key.value().to_string(), "optional"),
+            ContainerField::Cores => ("Cores", app.container_cores.value().to_string(), ""),
+            ContainerField::Memory => ("Memory (MiB)", app.container_memory.value().to_string(), ""),
+            ContainerField::DiskSize => ("Disk size (GiB)", app.container_disk.value().to_string(), ""),
+            ContainerField::Storage => (
+                "Disk storage",
+                match app.container_options.storages.get(app.container_storage_index) {
+                    Some(storage) => match storage.avail {
+                        Some(avail) => format!("< {} > {} free", storage.storage, format_bytes(avail)),
+                        None => choice(Some(&storage.storage)),
+                    },
+                    None => String::new(),
+                },
+                if loading { "loading..." } else { "none on this node" },
+            ),
+            ContainerField::Bridge => ("Bridge", app.container_bridge.value().to_string(), ""),
+            ContainerField::StaticIp => (
+                "IPv4",
+                format!("< {} >", if app.container_static { "static" } else { "DHCP" }),
+                "",
+            ),
+            ContainerField::Address => ("Address", app.container_address.value().to_string(), "e.g. 10.0.0.5/24"),
+            ContainerField::Gateway => ("Gateway", app.container_gateway.value().to_string(), "optional"),
+            ContainerField::AddOverride => (
+                "IP override",
+                format!("[{}] save the address to ip_overrides", check(app.container_add_override)),
+                "",
+            ),
+            ContainerField::Start => ("Start", format!("[{}] after creating", check(app.container_start)), ""),
+        };
+
+        let active = app.container_field == *field;
+        let value_style = if active {
+            Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(Color::White)
+        };
+        let mut spans = vec![label(name), Span::styled(value.clone(), value_style)];
+        if value.is_empty() && !hint.is_empty() {
+            spans.push(Span::styled(hint, Style::default().fg(Color::DarkGray)));
+        }
+        f.render_widget(Paragraph::new(Line::from(spans)), *area);
+
+        if active {
+            let cursor = match field {
+                ContainerField::Hostname => Some(&app.container_hostname),
+                ContainerField::Vmid => Some(&app.container_vmid),
+                ContainerField::SshKey => Some(&app.container_ssh_key),
+                ContainerField::Cores => Some(&app.container_cores),
+                ContainerField::Memory => Some(&app.container_memory),
+                ContainerField::DiskSize => Some(&app.container_disk),
+                ContainerField::Bridge => Some(&app.container_bridge),
+                ContainerField::Address => Some(&app.container_address),
+                ContainerField::Gateway => Some(&app.container_gateway),
+                _ => None,
+            };
+            if let Some(input) = cursor {
+                f.set_cursor_position((area.x + (LABEL_WIDTH + input.visual_cursor()) as u16, area.y));
+            }
+        }
+    }
+
+    let status_row = summary.len() + fields.len() + 1;
+    if let Some(message) = &app.container_message {
+        f.render_widget(
+            Paragraph::new(message.as_str()).style(Style::default().fg(Color::Red)),
+            chunks[status_row],
+        );
+    }
+
+    let review = app.container_step == ContainerStep::Review;
+    let instructions = Paragraph::new(
+        Line::from(vec![
+            Span::styled("Enter", Style::default().fg(Color::Green).add_modifier(Modifier::BOLD)),
+            Span::styled(if review { ": Create | " } else { ": Next | " }, Style::default()),
+            Span::styled("Tab/↑/↓", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+            Span::styled(": Field | ", Style::default()),
+            Span::styled("Space/←/→", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+            Span::styled(": Change choice | ", Style::default()),
+            Span::styled("Esc", Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)),
+            Span::styled(
+                if app.container_step.number() == 1 { ": Cancel" } else { ": Back" },
+                Style::default(),
+            ),
+        ])
+    );
+    f.render_widget(instructions, chunks[status_row + 1]);
+}
+
+fn render_tasks_view(f: &mut Frame, app: &App) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Min(0),     // Task table
+            Constraint::Length(3),  // Keybindings
+        ])
+        .split(f.area());
+
+    let header_cells = ["Status", "Started", "Cluster", "Node", "Type", "Target", "User", "Description"]
+        .iter()
+        .map(|h| Cell::from(*h).style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)));
+    let header = Row::new(header_cells).height(1).bottom_margin(1);
+
+    let rows = app.tasks.tasks.iter().map(|task| {
+        let status_style = match task.state {
+            TaskState::Running => Style::default().fg(Color::Yellow),
+            TaskState::Ok => Style::default().fg(Color::Green),
+            TaskState::Warnings(_) => Style::default().fg(Color::LightYellow),
+            TaskState::Failed(_) => Style::default().fg(Color::Red),
+        };
+        Row::new(vec![
+            Cell::from(task.state.as_str().to_string()).style(status_style),
+            Cell::from(task.age()),
+            Cell::from(task.cluster.clone()),
+            Cell::from(task.node.clone()),
+            Cell::from(task.task_type.clone()),
+            Cell::from(task.target.clone()),
+            Cell::from(task.user.clone()),
+            Cell::from(task.description.clone()),
+        ])
+        .height(1)
+    });
+
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Percentage(15),
+            Constraint::Percentage(10),
+            Constraint::Percentage(10),
+            Constraint::Percentage(10),
+            Constraint::Percentage(12),
+            Constraint::Percentage(8),
+            Constraint::Percentage(12),
+            Constraint::Percentage(23),
+        ],
+    )
+    .header(header)
+    .block(Block::default().borders(Borders::ALL).title(" Proxmox Tasks "))
+    .row_highlight_style(
+        Style::default()
+            .bg(Color::DarkGray)
+            .add_modifier(Modifier::BOLD),
+    )
+    .highlight_symbol(">> ");
+
+    let mut table_state = TableState::default();
+    if !app.tasks.tasks.is_empty() {
+        table_state.select(Some(app.task_selected));
+    }
+    f.render_stateful_widget(table, chunks[0], &mut table_state);
+
+    let instructions = Paragraph::new(Line::from(vec![
+        Span::styled("↑/↓", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+        Span::raw(": Nav | "),
+        Span::styled("Enter", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+        Span::raw(": View log | "),
+        Span::styled("Esc/q", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+        Span::raw(": Back"),
+    ]))
+    .block(Block::default().borders(Borders::ALL));
+    f.render_widget(instructions, chunks[1]);
+}
+
+fn render_task_log_view(f: &mut Frame, app: &App) {
+    let Some(task) = app.tasks.tasks.get(app.task_selected) else {
+        return;
+    };
+
+    let area = centered_rect(80, 80, f.area());
+
+    let block = Block::default()
+        .title(format!(" {} [{}] ", task.description, task.state.as_str()))
+        .borders(Borders::ALL)
+        .style(Style::default().bg(Color::Black));
+
+    let inner_area = block.inner(area);
+    f.render_widget(block, area);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Min(0),     // Log
+            Constraint::Length(1),  // Instructions
+        ])
+        .split(inner_area);
+
+    let lines: Vec<Line> = task
+        .log
+        .iter()
+        .skip(app.task_log_scroll)
+        .map(|l| Line::from(l.as_str()))
+        .collect();
+
+    let log = Paragraph::new(lines).style(Style::default().fg(Color::White));
+    f.render_widget(log, chunks[0]);
+
+    let instructions = Paragraph::new(Line::from(vec![
+        Span::styled("↑/↓ PgUp/PgDn g/G", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+        Span::styled(": Scroll | ", Style::default()),
+        Span::styled("Enter/Esc/q", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+        Span::styled(": Close", Style::default()),
+    ]));
+    f.render_widget(instructions, chunks[1]);
+}
+
+/// Format a byte count using binary units, e.g. "1.5 GiB"
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+/// Format an uptime in seconds as e.g. "3d 4h" or "12m"
+fn format_uptime(secs: u64) -> String {
+    let days = secs / 86400;
+    let hours = (secs % 86400) / 3600;
+    let minutes = (secs % 3600) / 60;
+    if days > 0 {
+        format!("{}d {}h", days, hours)
+    } else if hours > 0 {
+        format!("{}h {}m", hours, minutes)
+    } else {
+        format!("{}m", minutes)
+    }
 }
 
 /// Helper function to create a centered rectangle with percentage sizing
@@ -454,8 +1785,7 @@ fn render_loading_screen(f: &mut Frame, app: &App) {
     f.render_widget(tagline, chunks[3]);
 
     // Animated spinner and loading message
-    let spinner_frames = ["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
-    let spinner = spinner_frames[app.loading_frame % spinner_frames.len()];
+    let spinner = SPINNER_FRAMES[app.loading_frame % SPINNER_FRAMES.len()];
 
     let loading_text = vec![
         Line::from(""),